@@ -33,7 +33,7 @@ use std::{
     path::PathBuf,
     sync::{Arc, Mutex, RwLock},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use conductor::passphrase_manager::{PassphraseManager, PassphraseServiceCmd};
@@ -42,6 +42,7 @@ use holochain_net::{
     p2p_config::P2pConfig,
 };
 use interface::{ConductorApiBuilder, InstanceMap, Interface};
+use signal_stats::SignalDropCounter;
 use signal_wrapper::SignalWrapper;
 use static_file_server::StaticServer;
 
@@ -87,10 +88,12 @@ pub struct Conductor {
     pub(in crate::conductor) interface_threads: HashMap<String, Sender<()>>,
     pub(in crate::conductor) interface_broadcasters: Arc<RwLock<HashMap<String, Broadcaster>>>,
     signal_multiplexer_kill_switch: Option<Sender<()>>,
+    signal_multiplexer_flushed: Option<Receiver<()>>,
     pub key_loader: KeyLoader,
     pub(in crate::conductor) dna_loader: DnaLoader,
     pub(in crate::conductor) ui_dir_copier: UiDirCopier,
     signal_tx: Option<SignalSender>,
+    connection_signal_tx: Option<Sender<SignalWrapper>>,
     logger: DebugLogger,
     p2p_config: Option<P2pConfig>,
     network_spawn: Option<SpawnResult>,
@@ -142,11 +145,13 @@ impl Conductor {
             static_servers: HashMap::new(),
             interface_broadcasters: Arc::new(RwLock::new(HashMap::new())),
             signal_multiplexer_kill_switch: None,
+            signal_multiplexer_flushed: None,
             config,
             key_loader: Arc::new(Box::new(Self::load_key)),
             dna_loader: Arc::new(Box::new(Self::load_dna)),
             ui_dir_copier: Arc::new(Box::new(Self::copy_ui_dir)),
             signal_tx: None,
+            connection_signal_tx: None,
             logger: DebugLogger::new(rules),
             p2p_config: None,
             network_spawn: None,
@@ -173,6 +178,13 @@ impl Conductor {
         self
     }
 
+    /// Subscribes the given channel to the direct connection events of all instances
+    /// (see Signal::is_direct_connection_event), independent of expose_trace_signals.
+    pub fn with_connection_signal_channel(mut self, tx: Sender<SignalWrapper>) -> Self {
+        self.connection_signal_tx = Some(tx);
+        self
+    }
+
     pub fn p2p_bindings(&self) -> Option<Vec<String>> {
         self.network_spawn
             .as_ref()
@@ -185,64 +197,70 @@ impl Conductor {
 
     /// Starts a new thread which monitors each instance's signal channel and pushes signals out
     /// all interfaces the according instance is part of.
+    /// When stopped, it first flushes the signals still pending in the instances' channels.
+    /// Signals that could not be delivered are counted and reported per instance as
+    /// Signal::SignalStats every SIGNAL_STATS_INTERVAL.
     pub fn start_signal_multiplexer(&mut self) -> thread::JoinHandle<()> {
         let broadcasters = self.interface_broadcasters.clone();
         let instance_signal_receivers = self.instance_signal_receivers.clone();
         let signal_tx = self.signal_tx.clone();
+        let connection_signal_tx = self.connection_signal_tx.clone();
         let config = self.config.clone();
         let (kill_switch_tx, kill_switch_rx) = unbounded();
+        let (flushed_tx, flushed_rx) = unbounded();
         self.signal_multiplexer_kill_switch = Some(kill_switch_tx);
+        self.signal_multiplexer_flushed = Some(flushed_rx);
 
         self.log("starting signal loop".into());
+        let mut dropped_signals = SignalDropCounter::new();
+        let mut last_stats = Instant::now();
         thread::spawn(move || loop {
             {
                 for (instance_id, receiver) in instance_signal_receivers.read().unwrap().iter() {
                     if let Ok(signal) = receiver.try_recv() {
-                        signal_tx.clone().map(|s| s.send(signal.clone()));
-                        let broadcasters = broadcasters.read().unwrap();
-                        let interfaces_with_instance: Vec<&InterfaceConfiguration> = match signal {
-                            // Send internal signals only to admin interfaces, if expose_trace_signals is set:
-                            Signal::Trace(_) => {
-                                if config.expose_trace_signals {
-                                    config
-                                        .interfaces
-                                        .iter()
-                                        .filter(|interface_config| interface_config.admin)
-                                        .collect()
-                                } else {
-                                    Vec::new()
-                                }
-                            }
-
-                            // Pass through user-defined  signals to the according interfaces
-                            // in which the source instance is exposed:
-                            Signal::User(_) => config
-                                .interfaces
-                                .iter()
-                                .filter(|interface_config| {
-                                    interface_config
-                                        .instances
-                                        .iter()
-                                        .find(|instance| instance.id == *instance_id)
-                                        .is_some()
-                                })
-                                .collect(),
-                        };
-
-                        for interface in interfaces_with_instance {
-                            broadcasters.get(&interface.id).map(|broadcaster| {
-                                if let Err(error) = broadcaster.send(SignalWrapper {
-                                    signal: signal.clone(),
-                                    instance_id: instance_id.clone(),
-                                }) {
-                                    notify(error.to_string());
-                                }
-                            });
-                        }
+                        route_signal(
+                            &config,
+                            &broadcasters,
+                            &signal_tx,
+                            &connection_signal_tx,
+                            &mut dropped_signals,
+                            instance_id,
+                            signal,
+                        );
                     }
                 }
             }
+            if last_stats.elapsed() >= SIGNAL_STATS_INTERVAL {
+                for stats in dropped_signals.unreported_stats() {
+                    route_signal(
+                        &config,
+                        &broadcasters,
+                        &signal_tx,
+                        &connection_signal_tx,
+                        &mut dropped_signals,
+                        &stats.instance_id,
+                        stats.signal,
+                    );
+                }
+                last_stats = Instant::now();
+            }
             if kill_switch_rx.try_recv().is_ok() {
+                flush_signal_receivers(
+                    &instance_signal_receivers.read().unwrap(),
+                    SIGNAL_FLUSH_TIMEOUT,
+                    |instance_id, signal| {
+                        route_signal(
+                            &config,
+                            &broadcasters,
+                            &signal_tx,
+                            &connection_signal_tx,
+                            &mut dropped_signals,
+                            instance_id,
+                            signal,
+                        )
+                    },
+                );
+                let _ = flushed_tx.send(());
                 break;
             }
             thread::sleep(Duration::from_millis(1));
@@ -347,13 +365,23 @@ impl Conductor {
         let _ = self
             .stop_all_instances()
             .map_err(|error| notify(format!("Error during shutdown: {}", error)));
+        self.flush_signals();
         self.stop_all_interfaces();
-        self.signal_multiplexer_kill_switch
-            .as_ref()
-            .map(|sender| sender.send(()));
         self.instances = HashMap::new();
     }
 
+    /// Stops the signal multiplexer after it has delivered all signals still pending,
+    /// waiting at most a bit longer than its flush timeout.
+    /// Call this before closing interfaces so clients get the instances' last signals.
+    pub fn flush_signals(&mut self) {
+        if let Some(kill_switch) = self.signal_multiplexer_kill_switch.take() {
+            let _ = kill_switch.send(());
+        }
+        if let Some(flushed) = self.signal_multiplexer_flushed.take() {
+            let _ = flushed.recv_timeout(SIGNAL_FLUSH_TIMEOUT + Duration::from_millis(100));
+        }
+    }
+
     pub fn spawn_network(&mut self) -> Result<SpawnResult, HolochainError> {
         let network_config = self
             .config
@@ -940,6 +968,104 @@ fn make_interface(interface_config: &InterfaceConfiguration) -> Box<Interface> {
     }
 }
 
+/// Upper bound for delivering the signals still pending when the signal multiplexer stops.
+const SIGNAL_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the signal multiplexer reports new counts of dropped signals.
+const SIGNAL_STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pushes a signal of the given instance out to all interfaces that should receive it.
+/// Deliveries that fail, or would block on a full connection signal channel, are counted
+/// in `dropped_signals`.
+fn route_signal(
+    config: &Configuration,
+    broadcasters: &RwLock<HashMap<String, Broadcaster>>,
+    signal_tx: &Option<SignalSender>,
+    connection_signal_tx: &Option<Sender<SignalWrapper>>,
+    dropped_signals: &mut SignalDropCounter,
+    instance_id: &String,
+    signal: Signal,
+) {
+    signal_tx.clone().map(|s| s.send(signal.clone()));
+    if signal.is_direct_connection_event() {
+        if let Some(ref s) = *connection_signal_tx {
+            dropped_signals.deliver(
+                s,
+                SignalWrapper {
+                    signal: signal.clone(),
+                    instance_id: instance_id.clone(),
+                },
+            );
+        }
+    }
+    let broadcasters = broadcasters.read().unwrap();
+    let interfaces_with_instance: Vec<&InterfaceConfiguration> = match signal {
+        // Send internal signals only to admin interfaces, if expose_trace_signals is set:
+        Signal::Trace(_) => {
+            if config.expose_trace_signals {
+                config
+                    .interfaces
+                    .iter()
+                    .filter(|interface_config| interface_config.admin)
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        }
+
+        // Pass through user-defined  signals (and stats about them)
+        // to the according interfaces in which the source instance is exposed:
+        Signal::User(_) | Signal::SignalStats { .. } => config
+            .interfaces
+            .iter()
+            .filter(|interface_config| {
+                interface_config
+                    .instances
+                    .iter()
+                    .find(|instance| instance.id == *instance_id)
+                    .is_some()
+            })
+            .collect(),
+    };
+
+    for interface in interfaces_with_instance {
+        broadcasters.get(&interface.id).map(|broadcaster| {
+            if let Err(error) = broadcaster.send(SignalWrapper {
+                signal: signal.clone(),
+                instance_id: instance_id.clone(),
+            }) {
+                dropped_signals.record_drop(instance_id);
+                notify(error.to_string());
+            }
+        });
+    }
+}
+
+/// Drains all pending signals of the given receivers, in the order they were emitted
+/// per instance, and hands them to `deliver`.
+/// Stops early once `timeout` has elapsed, so that a busy instance can not hold up a shutdown.
+/// Returns the number of delivered signals.
+pub(crate) fn flush_signal_receivers<F: FnMut(&String, Signal)>(
+    receivers: &HashMap<String, Receiver<Signal>>,
+    timeout: Duration,
+    mut deliver: F,
+) -> usize {
+    let deadline = Instant::now() + timeout;
+    let mut delivered = 0;
+    for (instance_id, receiver) in receivers.iter() {
+        while Instant::now() < deadline {
+            match receiver.try_recv() {
+                Ok(signal) => {
+                    deliver(instance_id, signal);
+                    delivered += 1;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+    delivered
+}
+
 #[derive(Clone, Debug)]
 struct NullLogger {}
 
@@ -956,7 +1082,9 @@ pub mod tests {
     extern crate tempfile;
     use crate::config::load_configuration;
     use holochain_core::{
-        action::Action, nucleus::actions::call_zome_function::make_cap_request_for_call,
+        action::{Action, ActionWrapper, DirectMessageData},
+        network::direct_message::DirectMessage,
+        nucleus::actions::call_zome_function::make_cap_request_for_call,
         signal::signal_channel,
     };
     use holochain_core_types::{cas::content::Address, dna, json::RawString};
@@ -1554,12 +1682,126 @@ pub mod tests {
         let received_signals = signals.lock().clone();
 
         assert!(received_signals.len() >= 3);
-        assert!(received_signals[0]
-            .starts_with("{\"signal\":{\"Trace\":\"SignalZomeFunctionCall(ZomeFnCall {"));
-        assert!(received_signals[1]
-            .starts_with("{\"signal\":{\"Trace\":\"SignalZomeFunctionCall(ZomeFnCall {"));
+        assert!(received_signals[0].starts_with(
+            "{\"signal\":{\"type\":\"Trace\",\"data\":{\"action\":{\"action_type\":\"SignalZomeFunctionCall\",\"data\":"
+        ));
+        assert!(received_signals[1].starts_with(
+            "{\"signal\":{\"type\":\"Trace\",\"data\":{\"action\":{\"action_type\":\"SignalZomeFunctionCall\",\"data\":"
+        ));
         assert!(received_signals[2].starts_with(
-            "{\"signal\":{\"Trace\":\"ReturnZomeFunctionResult(ExecuteZomeFnResponse {"
+            "{\"signal\":{\"type\":\"Trace\",\"data\":{\"action\":{\"action_type\":\"ReturnZomeFunctionResult\",\"data\":"
         ));
     }
+
+    #[test]
+    fn test_flush_signal_receivers_delivers_pending_signals_in_order() {
+        let (signal_tx, signal_rx) = signal_channel();
+        for n in 0..5 {
+            signal_tx
+                .send(Signal::User(JsonString::from(json!({ "n": n }))))
+                .unwrap();
+        }
+        let mut receivers = HashMap::new();
+        receivers.insert("app".to_string(), signal_rx);
+
+        let mut delivered = Vec::new();
+        let count =
+            flush_signal_receivers(
+                &receivers,
+                Duration::from_secs(1),
+                |id, signal| match signal {
+                    Signal::User(payload) => delivered.push((id.clone(), String::from(payload))),
+                    _ => panic!("expected a user signal"),
+                },
+            );
+
+        assert_eq!(count, 5);
+        let expected: Vec<(String, String)> = (0..5)
+            .map(|n| ("app".to_string(), format!("{{\"n\":{}}}", n)))
+            .collect();
+        assert_eq!(delivered, expected);
+        // nothing is left to flush:
+        assert_eq!(
+            flush_signal_receivers(&receivers, Duration::from_secs(1), |_, _| ()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_route_signal_streams_direct_connection_events() {
+        let (connection_tx, connection_rx) = unbounded();
+        let config = Configuration::default();
+        let broadcasters = RwLock::new(HashMap::new());
+        let instance_id = "app".to_string();
+        let open = Action::SendDirectMessage(DirectMessageData {
+            address: Address::from("peer"),
+            message: DirectMessage::RequestValidationPackage(Address::from("entry")),
+            msg_id: "connection".to_string(),
+            is_response: false,
+        });
+        let touch = Action::TouchDirectConnection("connection".to_string());
+        let resolve = Action::ResolveDirectConnection(("connection".to_string(), Ok(())));
+        let signals = vec![
+            Signal::Trace(ActionWrapper::new(open.clone())),
+            Signal::User(JsonString::from(json!({ "n": 0 }))),
+            Signal::Trace(ActionWrapper::new(touch.clone())),
+            Signal::Trace(ActionWrapper::new(Action::Publish(Address::from("entry")))),
+            Signal::Trace(ActionWrapper::new(resolve.clone())),
+        ];
+        let mut dropped_signals = SignalDropCounter::new();
+        for signal in signals {
+            route_signal(
+                &config,
+                &broadcasters,
+                &None,
+                &Some(connection_tx.clone()),
+                &mut dropped_signals,
+                &instance_id,
+                signal,
+            );
+        }
+        assert_eq!(dropped_signals.dropped("app"), 0);
+
+        let streamed: Vec<Action> = connection_rx
+            .try_iter()
+            .map(|wrapper| {
+                assert_eq!(wrapper.instance_id, "app");
+                match wrapper.signal {
+                    Signal::Trace(action_wrapper) => action_wrapper.action().clone(),
+                    _ => panic!("expected a trace signal"),
+                }
+            })
+            .collect();
+        assert_eq!(streamed, vec![open, touch, resolve]);
+    }
+
+    #[test]
+    fn test_route_signal_counts_signals_dropped_on_full_connection_channel() {
+        let (connection_tx, connection_rx) = crossbeam_channel::bounded(1);
+        let config = Configuration::default();
+        let broadcasters = RwLock::new(HashMap::new());
+        let instance_id = "app".to_string();
+        let resolve = Action::ResolveDirectConnection(("connection".to_string(), Ok(())));
+        let mut dropped_signals = SignalDropCounter::new();
+        for _ in 0..3 {
+            route_signal(
+                &config,
+                &broadcasters,
+                &None,
+                &Some(connection_tx.clone()),
+                &mut dropped_signals,
+                &instance_id,
+                Signal::Trace(ActionWrapper::new(resolve.clone())),
+            );
+        }
+        assert_eq!(connection_rx.try_iter().count(), 1);
+        assert_eq!(dropped_signals.dropped("app"), 2);
+
+        let stats = dropped_signals.unreported_stats();
+        assert_eq!(stats.len(), 1);
+        match stats[0].signal {
+            Signal::SignalStats { dropped, .. } => assert_eq!(dropped, 2),
+            _ => panic!("expected a SignalStats signal"),
+        }
+    }
 }
@@ -24,7 +24,7 @@ use conductor::passphrase_manager::PassphraseManager;
 use holochain_dpki::{password_encryption::PwHashConfig, seed::SeedType};
 use std::{
     collections::{BTreeMap, HashMap},
-    fs::File,
+    fs::{self, File},
     io::prelude::*,
     path::PathBuf,
     sync::{Arc, Mutex},
@@ -50,6 +50,14 @@ pub enum KeyType {
     Encrypting,
 }
 
+/// How [Keystore::merge] resolves identifiers that exist in both keystores
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    KeepExisting,
+    TakeIncoming,
+    Error,
+}
+
 /// A type for providing high-level crypto functions and managing secrets securely.
 /// Keystore can store an arbitrary number of named secrets such as key pairs and seeds.
 /// It can be serialized and deserialized with serde and stores secrets in encrypted [KeyBlob]s,
@@ -103,6 +111,19 @@ pub struct Keystore {
     hash_config: Option<PwHashConfig>,
 }
 
+const CHUNK_INDEX_FILE: &str = "keystore.index";
+
+/// Describes a keystore saved with [Keystore::save_chunked]
+#[derive(Serialize, Deserialize)]
+struct ChunkIndex {
+    chunk_count: usize,
+    total_size: usize,
+}
+
+fn chunk_file_name(i: usize) -> String {
+    format!("keystore.chunk.{}", i)
+}
+
 fn make_passphrase_check(
     passphrase: &mut SecBuf,
     hash_config: Option<PwHashConfig>,
@@ -155,7 +176,44 @@ impl Keystore {
         let mut file = File::open(path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        let mut keystore: Keystore = serde_json::from_str(&contents)?;
+        Keystore::from_json(&contents, passphrase_manager, hash_config)
+    }
+
+    /// Load a keystore that was saved with [save_chunked] into the directory `dir`.
+    /// Like [new_from_file], secrets stay encrypted until requested.
+    pub fn new_from_chunks(
+        dir: PathBuf,
+        passphrase_manager: Arc<PassphraseManager>,
+        hash_config: Option<PwHashConfig>,
+    ) -> HcResult<Self> {
+        let mut index_file = File::open(dir.join(CHUNK_INDEX_FILE))?;
+        let mut index_json = String::new();
+        index_file.read_to_string(&mut index_json)?;
+        let index: ChunkIndex = serde_json::from_str(&index_json)?;
+
+        let mut contents = Vec::with_capacity(index.total_size);
+        for i in 0..index.chunk_count {
+            let mut chunk_file = File::open(dir.join(chunk_file_name(i)))?;
+            chunk_file.read_to_end(&mut contents)?;
+        }
+        if contents.len() != index.total_size {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Keystore chunks add up to {} bytes but index expects {}",
+                contents.len(),
+                index.total_size
+            )));
+        }
+        let contents =
+            String::from_utf8(contents).map_err(|e| HolochainError::ErrorGeneric(e.to_string()))?;
+        Keystore::from_json(&contents, passphrase_manager, hash_config)
+    }
+
+    fn from_json(
+        contents: &str,
+        passphrase_manager: Arc<PassphraseManager>,
+        hash_config: Option<PwHashConfig>,
+    ) -> HcResult<Self> {
+        let mut keystore: Keystore = serde_json::from_str(contents)?;
         keystore.hash_config = hash_config;
         keystore.passphrase_manager = Some(passphrase_manager);
         Ok(keystore)
@@ -286,6 +344,31 @@ impl Keystore {
         Ok(())
     }
 
+    /// Serialize the keystore into the directory `dir`, split into files of at most
+    /// `chunk_size` bytes plus an index file, for storage backends with size limits.
+    /// Load it again with [new_from_chunks].
+    pub fn save_chunked(&self, dir: PathBuf, chunk_size: usize) -> HcResult<()> {
+        if chunk_size == 0 {
+            return Err(HolochainError::ErrorGeneric(
+                "Keystore chunk size must not be 0".to_string(),
+            ));
+        }
+        let json_string = serde_json::to_string(self)?;
+        let chunks: Vec<&[u8]> = json_string.as_bytes().chunks(chunk_size).collect();
+        fs::create_dir_all(&dir)?;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let mut file = File::create(dir.join(chunk_file_name(i)))?;
+            file.write_all(chunk)?;
+        }
+        let index = ChunkIndex {
+            chunk_count: chunks.len(),
+            total_size: json_string.len(),
+        };
+        let mut file = File::create(dir.join(CHUNK_INDEX_FILE))?;
+        file.write_all(serde_json::to_string(&index)?.as_bytes())?;
+        Ok(())
+    }
+
     /// return a list of the identifiers stored in the keystore
     pub fn list(&self) -> Vec<String> {
         self.secrets.keys().map(|k| k.to_string()).collect()
@@ -299,6 +382,34 @@ impl Keystore {
         Ok(())
     }
 
+    /// Adds all secrets of `other` to this keystore, e.g. to restore a backup.
+    /// Incoming secrets get decrypted with the passphrase of `other` and encrypted again
+    /// with the passphrase of this keystore, so the passphrases don't need to match.
+    /// With [ConflictPolicy::Error] nothing gets merged if any identifier exists in both.
+    pub fn merge(&mut self, mut other: Keystore, on_conflict: ConflictPolicy) -> HcResult<()> {
+        let conflicts: Vec<String> = other
+            .list()
+            .into_iter()
+            .filter(|id| self.secrets.contains_key(id))
+            .collect();
+        if on_conflict == ConflictPolicy::Error && !conflicts.is_empty() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "identifiers exist in both keystores: {}",
+                conflicts.join(", ")
+            )));
+        }
+        for id in other.list() {
+            if on_conflict == ConflictPolicy::KeepExisting && conflicts.contains(&id) {
+                continue;
+            }
+            let secret = other.get(&id)?;
+            self.secrets.remove(&id);
+            self.cache.insert(id.clone(), secret);
+            self.encrypt(&id)?;
+        }
+        Ok(())
+    }
+
     /// adds a random root seed into the keystore
     pub fn add_random_seed(&mut self, dst_id_str: &str, size: usize) -> HcResult<()> {
         let dst_id = self.check_dst_identifier(dst_id_str)?;
@@ -529,6 +640,34 @@ impl Keystore {
             }
         }
     }
+
+    /// signs some data with every signing key in the keystore, e.g. for attestations
+    /// all hosted agents have to sign
+    /// returns the public key (i.e. agent id) of every signing key with its signature
+    pub fn sign_all(&mut self, data: &mut SecBuf) -> HcResult<Vec<(Base32, SecBuf)>> {
+        let signing_key_ids: Vec<String> = self
+            .secrets
+            .iter()
+            .filter(|&(_, blob)| blob.blob_type == BlobType::SigningKey)
+            .map(|(id, _)| id.clone())
+            .collect();
+        signing_key_ids
+            .iter()
+            .map(|id| {
+                let secret = self.get(id)?;
+                let mut secret = secret.lock().unwrap();
+                match *secret {
+                    Secret::SigningKey(ref mut key_pair) => {
+                        Ok((key_pair.public(), key_pair.sign(data)?))
+                    }
+                    _ => Err(HolochainError::ErrorGeneric(format!(
+                        "secret {} is not a signing key",
+                        id
+                    ))),
+                }
+            })
+            .collect()
+    }
 }
 
 pub fn test_hash_config() -> Option<PwHashConfig> {
@@ -617,6 +756,60 @@ pub mod tests {
         assert_eq!(expected_seed, loaded_seed);
     }
 
+    #[test]
+    fn test_save_load_chunked_roundtrip() {
+        let random_passphrase = random_test_passphrase();
+        let mut keystore = new_test_keystore(random_passphrase.clone());
+        keystore.add_random_seed("root_seed", SEED_SIZE).unwrap();
+        for i in 1..4 {
+            let seed_id = format!("seed_{}", i);
+            keystore
+                .add_seed_from_seed("root_seed", &seed_id, &SeedContext::new(*b"TESTSEED"), i)
+                .unwrap();
+            keystore
+                .add_keybundle_from_seed(&seed_id, &format!("bundle_{}", i))
+                .unwrap();
+        }
+
+        let mut path = PathBuf::new();
+        path.push("tmp-test/test-keystore-chunks");
+        let _ = fs::remove_dir_all(&path);
+        keystore.save_chunked(path.clone(), 100).unwrap();
+
+        let index: ChunkIndex =
+            serde_json::from_str(&fs::read_to_string(path.join(CHUNK_INDEX_FILE)).unwrap())
+                .unwrap();
+        assert!(index.chunk_count > 1);
+        for i in 0..index.chunk_count {
+            assert!(fs::metadata(path.join(chunk_file_name(i))).unwrap().len() <= 100);
+        }
+
+        let mut loaded_keystore = Keystore::new_from_chunks(
+            path.clone(),
+            mock_passphrase_manager(random_passphrase),
+            test_hash_config(),
+        )
+        .unwrap();
+        assert_eq!(loaded_keystore.list(), keystore.list());
+
+        let (sign_key, _) = keystore.add_keybundle_from_seed("seed_2", "check").unwrap();
+        let (loaded_sign_key, _) = loaded_keystore
+            .add_keybundle_from_seed("seed_2", "check")
+            .unwrap();
+        assert_eq!(sign_key, loaded_sign_key);
+
+        // a missing chunk is detected
+        fs::remove_file(path.join(chunk_file_name(index.chunk_count - 1))).unwrap();
+        assert!(Keystore::new_from_chunks(
+            path.clone(),
+            mock_passphrase_manager(random_test_passphrase()),
+            test_hash_config(),
+        )
+        .is_err());
+
+        assert!(keystore.save_chunked(path, 0).is_err());
+    }
+
     #[test]
     fn test_keystore_change_passphrase() {
         let random_passphrase = random_test_passphrase();
@@ -655,6 +848,87 @@ pub mod tests {
         );
     }
 
+    fn seed_bytes(keystore: &mut Keystore, id: &str) -> Vec<u8> {
+        let secret = keystore.get(id).unwrap();
+        let mut secret = secret.lock().unwrap();
+        match *secret {
+            Secret::Seed(ref mut buf) => buf.read_lock().to_vec(),
+            _ => panic!("expected a seed"),
+        }
+    }
+
+    /// an existing keystore holding "shared" and "existing_only", and an incoming one
+    /// with another passphrase holding "shared" and "incoming_only"
+    fn keystores_to_merge() -> (Keystore, Keystore) {
+        let mut existing = new_test_keystore(random_test_passphrase());
+        existing.add_random_seed("shared", SEED_SIZE).unwrap();
+        existing
+            .add_random_seed("existing_only", SEED_SIZE)
+            .unwrap();
+        let mut incoming = new_test_keystore(random_test_passphrase());
+        incoming.add_random_seed("shared", SEED_SIZE).unwrap();
+        incoming
+            .add_random_seed("incoming_only", SEED_SIZE)
+            .unwrap();
+        (existing, incoming)
+    }
+
+    #[test]
+    fn test_keystore_merge_keep_existing() {
+        let (mut existing, mut incoming) = keystores_to_merge();
+        let shared = seed_bytes(&mut existing, "shared");
+        let incoming_only = seed_bytes(&mut incoming, "incoming_only");
+
+        existing
+            .merge(incoming, ConflictPolicy::KeepExisting)
+            .unwrap();
+        assert_eq!(
+            existing.list(),
+            vec!["existing_only", "incoming_only", "shared"]
+        );
+        assert_eq!(seed_bytes(&mut existing, "shared"), shared);
+        assert_eq!(seed_bytes(&mut existing, "incoming_only"), incoming_only);
+
+        // only the newly encrypted blob has to decrypt with the keystore's passphrase:
+        existing.cache.clear();
+        assert_eq!(seed_bytes(&mut existing, "incoming_only"), incoming_only);
+    }
+
+    #[test]
+    fn test_keystore_merge_take_incoming() {
+        let (mut existing, mut incoming) = keystores_to_merge();
+        let existing_only = seed_bytes(&mut existing, "existing_only");
+        let shared = seed_bytes(&mut incoming, "shared");
+
+        existing
+            .merge(incoming, ConflictPolicy::TakeIncoming)
+            .unwrap();
+        existing.cache.clear();
+        assert_eq!(
+            existing.list(),
+            vec!["existing_only", "incoming_only", "shared"]
+        );
+        assert_eq!(seed_bytes(&mut existing, "shared"), shared);
+        assert_eq!(seed_bytes(&mut existing, "existing_only"), existing_only);
+    }
+
+    #[test]
+    fn test_keystore_merge_error_on_conflict() {
+        let (mut existing, incoming) = keystores_to_merge();
+        assert_eq!(
+            existing.merge(incoming, ConflictPolicy::Error),
+            Err(HolochainError::ErrorGeneric(
+                "identifiers exist in both keystores: shared".to_string()
+            ))
+        );
+        assert_eq!(existing.list(), vec!["existing_only", "shared"]);
+
+        let mut disjoint = new_test_keystore(random_test_passphrase());
+        disjoint.add_random_seed("other", SEED_SIZE).unwrap();
+        existing.merge(disjoint, ConflictPolicy::Error).unwrap();
+        assert_eq!(existing.list(), vec!["existing_only", "other", "shared"]);
+    }
+
     #[test]
     fn test_keystore_add_seed_from_seed() {
         let mut keystore = new_test_keystore(random_test_passphrase());
@@ -751,6 +1025,32 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_keystore_sign_all() {
+        let mut keystore = new_test_keystore(random_test_passphrase());
+        let mut data = SecBuf::with_insecure_from_string("the data to sign".to_string());
+        assert!(keystore.sign_all(&mut data).unwrap().is_empty());
+
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        let _ = keystore.add_random_seed("my_other_seed", SEED_SIZE);
+        let alice = keystore
+            .add_signing_key_from_seed("my_root_seed", "alice")
+            .unwrap();
+        let bob = keystore
+            .add_signing_key_from_seed("my_other_seed", "bob")
+            .unwrap();
+        keystore
+            .add_encrypting_key_from_seed("my_root_seed", "alice_enc")
+            .unwrap();
+
+        let signatures = keystore.sign_all(&mut data).unwrap();
+        let signers: Vec<Base32> = signatures.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(signers, vec![alice, bob]);
+        for (id, mut signature) in signatures {
+            assert!(utils::verify_bufs(id, &mut data, &mut signature).unwrap());
+        }
+    }
+
     #[test]
     fn test_keystore_keybundle() {
         let mut keystore = new_test_keystore(random_test_passphrase());
@@ -145,6 +145,9 @@ pub mod interface_impls;
 pub mod key_loaders;
 pub mod keystore;
 pub mod logger;
+pub mod signal_buffer;
+pub mod signal_stats;
+pub mod signal_throttle;
 pub mod signal_wrapper;
 pub mod static_file_server;
 
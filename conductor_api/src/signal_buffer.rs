@@ -0,0 +1,130 @@
+use signal_wrapper::SignalWrapper;
+use std::collections::{HashMap, VecDeque};
+
+/// Sequence number of a signal within the stream of one instance.
+/// Starts at 1 so that 0 can be used to ask for everything still buffered.
+pub type SignalSeq = u64;
+
+/// Keeps the most recent signals of each instance so that clients that
+/// reconnect can catch up on what they missed while disconnected.
+///
+/// The buffer is bounded: once `capacity` signals of an instance are held,
+/// the oldest one is dropped for each new one.
+pub struct SignalBuffer {
+    capacity: usize,
+    buffers: HashMap<String, VecDeque<(SignalSeq, SignalWrapper)>>,
+    last_seqs: HashMap<String, SignalSeq>,
+}
+
+impl SignalBuffer {
+    pub fn new(capacity: usize) -> Self {
+        SignalBuffer {
+            capacity,
+            buffers: HashMap::new(),
+            last_seqs: HashMap::new(),
+        }
+    }
+
+    /// Stores the signal and returns the sequence number it got assigned.
+    pub fn push(&mut self, signal: SignalWrapper) -> SignalSeq {
+        let seq = {
+            let last_seq = self
+                .last_seqs
+                .entry(signal.instance_id.clone())
+                .or_insert(0);
+            *last_seq += 1;
+            *last_seq
+        };
+        let buffer = self
+            .buffers
+            .entry(signal.instance_id.clone())
+            .or_insert_with(VecDeque::new);
+        if buffer.len() >= self.capacity {
+            buffer.pop_front();
+        }
+        if self.capacity > 0 {
+            buffer.push_back((seq, signal));
+        }
+        seq
+    }
+
+    /// Returns all buffered signals of the given instance with a sequence
+    /// number greater than `seq`, oldest first.
+    pub fn replay_since(
+        &self,
+        instance_id: &str,
+        seq: SignalSeq,
+    ) -> Vec<(SignalSeq, SignalWrapper)> {
+        self.buffers
+            .get(instance_id)
+            .map(|buffer| {
+                buffer
+                    .iter()
+                    .filter(|(signal_seq, _)| *signal_seq > seq)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sequence number of the latest signal of the given instance, if any.
+    pub fn last_seq(&self, instance_id: &str) -> Option<SignalSeq> {
+        self.last_seqs.get(instance_id).cloned()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core::signal::Signal;
+    use holochain_core_types::json::JsonString;
+
+    fn user_signal(instance_id: &str, n: usize) -> SignalWrapper {
+        SignalWrapper {
+            signal: Signal::User(JsonString::from(json!({ "n": n }))),
+            instance_id: instance_id.to_string(),
+        }
+    }
+
+    fn payloads(signals: Vec<(SignalSeq, SignalWrapper)>) -> Vec<(SignalSeq, String)> {
+        signals
+            .into_iter()
+            .map(|(seq, wrapper)| match wrapper.signal {
+                Signal::User(payload) => (seq, String::from(payload)),
+                _ => panic!("expected a user signal"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_signal_buffer_replays_after_reconnect() {
+        let mut buffer = SignalBuffer::new(3);
+        assert_eq!(buffer.push(user_signal("app", 0)), 1);
+        assert_eq!(buffer.push(user_signal("other", 0)), 1);
+        // client received seq 1 and disconnects
+        let seen = buffer.last_seq("app").unwrap();
+        assert_eq!(buffer.push(user_signal("app", 1)), 2);
+        assert_eq!(buffer.push(user_signal("app", 2)), 3);
+
+        assert_eq!(
+            payloads(buffer.replay_since("app", seen)),
+            vec![(2, "{\"n\":1}".to_string()), (3, "{\"n\":2}".to_string())],
+        );
+        assert_eq!(buffer.replay_since("app", 3).len(), 0);
+        assert_eq!(buffer.replay_since("unknown", 0).len(), 0);
+    }
+
+    #[test]
+    fn test_signal_buffer_is_bounded() {
+        let mut buffer = SignalBuffer::new(2);
+        for n in 0..5 {
+            buffer.push(user_signal("app", n));
+        }
+        assert_eq!(buffer.last_seq("app"), Some(5));
+        assert_eq!(
+            payloads(buffer.replay_since("app", 0)),
+            vec![(4, "{\"n\":3}".to_string()), (5, "{\"n\":4}".to_string())],
+        );
+        assert_eq!(payloads(buffer.replay_since("other", 0)), vec![]);
+    }
+}
@@ -0,0 +1,138 @@
+use crossbeam_channel::{Sender, TrySendError};
+use holochain_core::signal::Signal;
+use signal_wrapper::SignalWrapper;
+use std::collections::HashMap;
+
+/// Delivers signals to bounded client channels without blocking and counts,
+/// per instance, the signals that had to be dropped because a channel was
+/// full (or gone).
+///
+/// The counts are exposed to clients through `Signal::SignalStats`, see
+/// [unreported_stats], which the conductor's signal multiplexer sends out periodically.
+#[derive(Default)]
+pub struct SignalDropCounter {
+    dropped: HashMap<String, u64>,
+    reported: HashMap<String, u64>,
+}
+
+impl SignalDropCounter {
+    pub fn new() -> Self {
+        SignalDropCounter::default()
+    }
+
+    /// Tries to send the signal, counting it as dropped if that is not possible
+    /// right away. Returns whether the signal got delivered.
+    pub fn deliver(&mut self, sender: &Sender<SignalWrapper>, signal: SignalWrapper) -> bool {
+        match sender.try_send(signal) {
+            Ok(()) => true,
+            Err(TrySendError::Full(signal)) | Err(TrySendError::Disconnected(signal)) => {
+                self.record_drop(&signal.instance_id);
+                false
+            }
+        }
+    }
+
+    /// Counts a signal of the given instance that could not be delivered
+    pub fn record_drop(&mut self, instance_id: &str) {
+        *self.dropped.entry(instance_id.to_string()).or_insert(0) += 1;
+    }
+
+    /// Number of signals of the given instance dropped so far
+    pub fn dropped(&self, instance_id: &str) -> u64 {
+        self.dropped.get(instance_id).cloned().unwrap_or(0)
+    }
+
+    /// A `Signal::SignalStats` for every instance that had signals dropped
+    pub fn stats_signals(&self) -> Vec<SignalWrapper> {
+        self.dropped
+            .iter()
+            .map(|(instance_id, dropped)| SignalWrapper {
+                signal: Signal::SignalStats {
+                    instance_id: instance_id.clone(),
+                    dropped: *dropped,
+                },
+                instance_id: instance_id.clone(),
+            })
+            .collect()
+    }
+
+    /// Like [stats_signals], but only for the instances that had signals dropped
+    /// since the last call, so that unchanged counts do not get sent out again.
+    pub fn unreported_stats(&mut self) -> Vec<SignalWrapper> {
+        let stats: Vec<SignalWrapper> = self
+            .stats_signals()
+            .into_iter()
+            .filter(|stats| match stats.signal {
+                Signal::SignalStats { dropped, .. } => {
+                    self.reported.get(&stats.instance_id).cloned().unwrap_or(0) < dropped
+                }
+                _ => false,
+            })
+            .collect();
+        for stats in stats.iter() {
+            self.reported
+                .insert(stats.instance_id.clone(), self.dropped(&stats.instance_id));
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crossbeam_channel::bounded;
+    use holochain_core_types::json::JsonString;
+
+    fn user_signal(instance_id: &str) -> SignalWrapper {
+        SignalWrapper {
+            signal: Signal::User(JsonString::from_json("{}")),
+            instance_id: instance_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_signal_drop_counter_counts_overflow() {
+        let (tx, rx) = bounded(1);
+        let mut counter = SignalDropCounter::new();
+        assert!(counter.stats_signals().is_empty());
+
+        assert!(counter.deliver(&tx, user_signal("app")));
+        assert!(!counter.deliver(&tx, user_signal("app")));
+        assert!(!counter.deliver(&tx, user_signal("app")));
+        assert_eq!(counter.dropped("app"), 2);
+        assert_eq!(counter.dropped("other"), 0);
+
+        // once the client catches up, signals get through again
+        rx.recv().unwrap();
+        assert!(counter.deliver(&tx, user_signal("app")));
+        assert_eq!(counter.dropped("app"), 2);
+
+        let stats = counter.stats_signals();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].instance_id, "app");
+        match stats[0].signal {
+            Signal::SignalStats {
+                ref instance_id,
+                dropped,
+            } => {
+                assert_eq!(instance_id, "app");
+                assert_eq!(dropped, 2);
+            }
+            _ => panic!("expected a SignalStats signal"),
+        }
+    }
+
+    #[test]
+    fn test_signal_drop_counter_reports_only_new_drops() {
+        let mut counter = SignalDropCounter::new();
+        counter.record_drop("app");
+        assert_eq!(counter.unreported_stats().len(), 1);
+        assert!(counter.unreported_stats().is_empty());
+
+        counter.record_drop("other");
+        let stats = counter.unreported_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].instance_id, "other");
+        assert_eq!(counter.dropped("app"), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use holochain_core::signal::Signal;
+use holochain_core_types::json::JsonString;
+use signal_wrapper::SignalWrapper;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Book-keeping of a single instance's current rate window.
+struct ThrottleWindow {
+    started: Instant,
+    passed: usize,
+    dropped: usize,
+}
+
+/// Limits the rate of signals that get passed on to clients, per instance.
+///
+/// At most `max_signals` signals of one instance pass within each `window`.
+/// Excess signals are dropped and counted. Once the window has elapsed a
+/// single summary signal ("dropped N signals") is emitted for that instance,
+/// so slow clients know they missed something without being flooded.
+pub struct SignalThrottle {
+    max_signals: usize,
+    window: Duration,
+    windows: HashMap<String, ThrottleWindow>,
+}
+
+impl SignalThrottle {
+    pub fn new(max_signals: usize, window: Duration) -> Self {
+        SignalThrottle {
+            max_signals,
+            window,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Returns the signals that should be sent out for the given one:
+    /// a pending drop summary of a previous window (if any) followed by the
+    /// signal itself if it is within the rate limit.
+    pub fn process(&mut self, signal: SignalWrapper) -> Vec<SignalWrapper> {
+        self.process_at(signal, Instant::now())
+    }
+
+    pub fn process_at(&mut self, signal: SignalWrapper, now: Instant) -> Vec<SignalWrapper> {
+        let mut out = Vec::new();
+        let window = self
+            .windows
+            .entry(signal.instance_id.clone())
+            .or_insert_with(|| ThrottleWindow {
+                started: now,
+                passed: 0,
+                dropped: 0,
+            });
+
+        if now.duration_since(window.started) >= self.window {
+            if window.dropped > 0 {
+                out.push(dropped_summary(&signal.instance_id, window.dropped));
+            }
+            window.started = now;
+            window.passed = 0;
+            window.dropped = 0;
+        }
+
+        if window.passed < self.max_signals {
+            window.passed += 1;
+            out.push(signal);
+        } else {
+            window.dropped += 1;
+        }
+        out
+    }
+
+    /// Emits drop summaries for all instances whose window has elapsed.
+    /// Meant to be called periodically so that summaries also go out when an
+    /// instance has stopped emitting signals.
+    pub fn flush(&mut self) -> Vec<SignalWrapper> {
+        self.flush_at(Instant::now())
+    }
+
+    pub fn flush_at(&mut self, now: Instant) -> Vec<SignalWrapper> {
+        let window_length = self.window;
+        self.windows
+            .iter_mut()
+            .filter(|(_, window)| now.duration_since(window.started) >= window_length)
+            .filter_map(|(instance_id, window)| {
+                let dropped = window.dropped;
+                window.started = now;
+                window.passed = 0;
+                window.dropped = 0;
+                if dropped > 0 {
+                    Some(dropped_summary(instance_id, dropped))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn dropped_summary(instance_id: &str, dropped: usize) -> SignalWrapper {
+    SignalWrapper {
+        signal: Signal::User(JsonString::from(json!({ "dropped_signals": dropped }))),
+        instance_id: instance_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn user_signal(instance_id: &str, n: usize) -> SignalWrapper {
+        SignalWrapper {
+            signal: Signal::User(JsonString::from(json!({ "n": n }))),
+            instance_id: instance_id.to_string(),
+        }
+    }
+
+    fn user_payload(wrapper: &SignalWrapper) -> String {
+        match wrapper.signal {
+            Signal::User(ref payload) => String::from(payload.clone()),
+            _ => panic!("expected a user signal"),
+        }
+    }
+
+    #[test]
+    fn test_signal_throttle_limits_burst_and_summarizes() {
+        let mut throttle = SignalThrottle::new(3, Duration::from_millis(100));
+        let start = Instant::now();
+
+        let passed: Vec<SignalWrapper> = (0..10)
+            .flat_map(|n| throttle.process_at(user_signal("app", n), start))
+            .collect();
+        assert_eq!(passed.len(), 3);
+        assert_eq!(user_payload(&passed[0]), "{\"n\":0}");
+        assert_eq!(user_payload(&passed[2]), "{\"n\":2}");
+
+        // other instances have their own budget:
+        assert_eq!(throttle.process_at(user_signal("other", 0), start).len(), 1);
+
+        let later = start + Duration::from_millis(100);
+        let out = throttle.process_at(user_signal("app", 10), later);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].instance_id, "app");
+        assert_eq!(user_payload(&out[0]), "{\"dropped_signals\":7}");
+        assert_eq!(user_payload(&out[1]), "{\"n\":10}");
+    }
+
+    #[test]
+    fn test_signal_throttle_flush_emits_pending_summaries() {
+        let mut throttle = SignalThrottle::new(1, Duration::from_millis(100));
+        let start = Instant::now();
+
+        for n in 0..5 {
+            throttle.process_at(user_signal("app", n), start);
+        }
+        throttle.process_at(user_signal("quiet", 0), start);
+
+        assert!(throttle.flush_at(start).is_empty());
+
+        let summaries = throttle.flush_at(start + Duration::from_millis(150));
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].instance_id, "app");
+        assert_eq!(user_payload(&summaries[0]), "{\"dropped_signals\":4}");
+
+        // the summary is only sent once:
+        assert!(throttle
+            .flush_at(start + Duration::from_millis(300))
+            .is_empty());
+    }
+}
@@ -1,10 +1,217 @@
-use holochain_core::signal::Signal;
+use holochain_core::{action::ActionWrapper, signal::Signal};
 use holochain_core_types::{error::HolochainError, json::JsonString};
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    Serialize, Serializer,
+};
+use serde_json::Value;
+use std::io::BufRead;
 
 /// This struct wraps a Signal from core before serializing and sending over
 /// an interface to the UI or other client.
-#[derive(Serialize, Deserialize, Debug, DefaultJson)]
+///
+/// The wrapped signal is serialized as `{"type": <variant>, "data": <content>}`,
+/// see `serialize_signal`. Clients rely on that shape, so it must stay stable
+/// when variants get added to `Signal`.
+/// Trace signals can only be written, not read back, see `deserialize_signal`.
+#[derive(Serialize, Deserialize, Debug, Clone, DefaultJson)]
 pub struct SignalWrapper {
+    #[serde(
+        serialize_with = "serialize_signal",
+        deserialize_with = "deserialize_signal"
+    )]
     pub signal: Signal,
     pub instance_id: String,
 }
+
+impl SignalWrapper {
+    /// Wraps a signal for the given instance, rejecting empty or whitespace-only
+    /// instance ids which clients would not be able to attribute the signal to.
+    /// Use this wherever the instance id does not come from a validated config.
+    pub fn try_new(signal: Signal, instance_id: String) -> Result<Self, HolochainError> {
+        if instance_id.trim().is_empty() {
+            return Err(HolochainError::ErrorGeneric(
+                "SignalWrapper needs a non-empty instance id".to_string(),
+            ));
+        }
+        Ok(SignalWrapper {
+            signal,
+            instance_id,
+        })
+    }
+
+    /// Reads newline delimited JSON, one wrapper per line, e.g. to replay a signal log.
+    /// Blank lines are skipped. A line that can't be read or parsed yields an error
+    /// naming its line number, and reading goes on with the next line.
+    pub fn read_ndjson<R: BufRead>(
+        reader: R,
+    ) -> impl Iterator<Item = Result<SignalWrapper, HolochainError>> {
+        reader
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                Err(error) => Some(Err(
+                    HolochainError::from(error).context(&format!("line {}", index + 1))
+                )),
+                Ok(ref line) if line.trim().is_empty() => None,
+                Ok(line) => Some(serde_json::from_str(&line).map_err(|error| {
+                    HolochainError::SerializationError(format!("line {}: {}", index + 1, error))
+                })),
+            })
+    }
+}
+
+/// Wire format of a wrapped signal: adjacently tagged, so that every variant
+/// has the same shape no matter what it holds.
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data")]
+enum SignalRepr<'a> {
+    Trace(&'a ActionWrapper),
+    User(Value),
+    SignalStats { instance_id: &'a str, dropped: u64 },
+}
+
+/// User payloads are embedded as JSON instead of as an escaped string,
+/// unless they are not valid JSON in which case they are passed on as a string.
+fn serialize_signal<S: Serializer>(signal: &Signal, serializer: S) -> Result<S::Ok, S::Error> {
+    let repr = match *signal {
+        Signal::Trace(ref action_wrapper) => SignalRepr::Trace(action_wrapper),
+        Signal::User(ref payload) => {
+            let payload = String::from(payload.clone());
+            SignalRepr::User(serde_json::from_str(&payload).unwrap_or(Value::String(payload)))
+        }
+        Signal::SignalStats {
+            ref instance_id,
+            dropped,
+        } => SignalRepr::SignalStats {
+            instance_id,
+            dropped,
+        },
+    };
+    repr.serialize(serializer)
+}
+
+/// Owned counterpart of `SignalRepr` for reading wrapped signals back.
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "data")]
+enum OwnedSignalRepr {
+    Trace(Value),
+    User(Value),
+    SignalStats { instance_id: String, dropped: u64 },
+}
+
+/// Inverse of `serialize_signal`, except for trace signals: actions can't be
+/// deserialized, so those are rejected.
+fn deserialize_signal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Signal, D::Error> {
+    match OwnedSignalRepr::deserialize(deserializer)? {
+        OwnedSignalRepr::Trace(_) => {
+            Err(de::Error::custom("trace signals can not be deserialized"))
+        }
+        // strings are passed on as they were before serialization, see serialize_signal
+        OwnedSignalRepr::User(Value::String(payload)) => {
+            Ok(Signal::User(JsonString::from_json(&payload)))
+        }
+        OwnedSignalRepr::User(payload) => Ok(Signal::User(JsonString::from(payload))),
+        OwnedSignalRepr::SignalStats {
+            instance_id,
+            dropped,
+        } => Ok(Signal::SignalStats {
+            instance_id,
+            dropped,
+        }),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core::action::Action;
+    use holochain_core_types::cas::content::Address;
+
+    fn user_signal() -> Signal {
+        Signal::User(JsonString::from("{}"))
+    }
+
+    #[test]
+    fn test_try_new_rejects_empty_instance_id() {
+        assert!(SignalWrapper::try_new(user_signal(), String::new()).is_err());
+        assert!(SignalWrapper::try_new(user_signal(), " \t".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_try_new_accepts_instance_id() {
+        let wrapper = SignalWrapper::try_new(user_signal(), "app".to_string()).unwrap();
+        assert_eq!(wrapper.instance_id, "app");
+    }
+
+    #[test]
+    fn test_signal_wrapper_wire_format() {
+        let trace = SignalWrapper {
+            signal: Signal::Trace(ActionWrapper::new(Action::Publish(Address::from("QmTest")))),
+            instance_id: "app".to_string(),
+        };
+        let trace: Value = serde_json::from_str(&String::from(JsonString::from(trace))).unwrap();
+        assert_eq!(trace["signal"]["type"], json!("Trace"));
+        assert_eq!(
+            trace["signal"]["data"]["action"],
+            json!({ "action_type": "Publish", "data": "QmTest" }),
+        );
+        assert_eq!(trace["instance_id"], json!("app"));
+
+        let user = SignalWrapper {
+            signal: Signal::User(JsonString::from(json!({ "n": 1 }))),
+            instance_id: "app".to_string(),
+        };
+        assert_eq!(
+            JsonString::from(user),
+            JsonString::from_json(
+                "{\"signal\":{\"type\":\"User\",\"data\":{\"n\":1}},\"instance_id\":\"app\"}"
+            ),
+        );
+
+        let stats = SignalWrapper {
+            signal: Signal::SignalStats {
+                instance_id: "app".to_string(),
+                dropped: 3,
+            },
+            instance_id: "app".to_string(),
+        };
+        assert_eq!(
+            JsonString::from(stats),
+            JsonString::from_json(
+                "{\"signal\":{\"type\":\"SignalStats\",\"data\":{\"instance_id\":\"app\",\"dropped\":3}},\"instance_id\":\"app\"}"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_read_ndjson() {
+        let ndjson = "{\"signal\":{\"type\":\"User\",\"data\":{\"n\":1}},\"instance_id\":\"app\"}
+
+not a signal
+{\"signal\":{\"type\":\"SignalStats\",\"data\":{\"instance_id\":\"app\",\"dropped\":3}},\"instance_id\":\"app\"}
+";
+        let read: Vec<Result<SignalWrapper, HolochainError>> =
+            SignalWrapper::read_ndjson(ndjson.as_bytes()).collect();
+        assert_eq!(read.len(), 3);
+
+        let user = read[0].as_ref().unwrap();
+        assert_eq!(user.instance_id, "app");
+        match user.signal {
+            Signal::User(ref payload) => assert_eq!(String::from(payload.clone()), "{\"n\":1}"),
+            _ => panic!("expected a user signal"),
+        }
+
+        match read[1] {
+            Err(HolochainError::SerializationError(ref message)) => {
+                assert!(message.starts_with("line 3:"))
+            }
+            _ => panic!("expected a serialization error"),
+        }
+
+        match read[2].as_ref().unwrap().signal {
+            Signal::SignalStats { dropped, .. } => assert_eq!(dropped, 3),
+            _ => panic!("expected signal stats"),
+        }
+    }
+}
@@ -146,6 +146,11 @@ pub enum Action {
     ///
     GetEntryTimeout(GetEntryKey),
 
+    /// Makes the network module forget about the get process with the given key.
+    /// Triggered when the future waiting for the result gets dropped, so that
+    /// neither the pending entry nor a late response stays in the state.
+    CancelGetEntry(GetEntryKey),
+
     /// get links from entry address and link_type name
     /// Last string is the stringified process unique id of this `hdk::get_links` call.
     GetLinks(GetLinksKey),
@@ -164,7 +169,24 @@ pub enum Action {
     /// Makes the network module forget about the direct message
     /// connection with the given ID.
     /// Triggered when we got an answer to our initial DM.
-    ResolveDirectConnection(String),
+    /// An Err carries the reason the connection failed, which gets kept in
+    /// NetworkState::recent_connection_failures.
+    ResolveDirectConnection((String, Result<(), String>)),
+
+    /// Like ResolveDirectConnection, but only if the response came from the agent
+    /// the connection was opened to (given as the Address).
+    /// Otherwise the connection stays open and the mismatch gets kept in
+    /// NetworkState::recent_peer_mismatches.
+    ResolveDirectConnectionFrom((String, Address, Result<(), String>)),
+
+    /// Resolves the direct message connections without activity for longer than
+    /// NetworkState::prune_on_read as failed. Custom messages waiting for a reply
+    /// time out like with SendDirectMessageTimeout.
+    PruneDirectConnections,
+
+    /// Marks activity on the direct message connection with the given ID,
+    /// so that it does not get pruned. No-op if there is no such connection.
+    TouchDirectConnection(String),
 
     /// Makes the network module DM the source of the given entry
     /// and prepare for receiveing an answer
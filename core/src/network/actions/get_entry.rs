@@ -45,7 +45,9 @@ pub async fn get_entry(
 }
 
 /// GetEntryFuture resolves to a HcResult<Entry>.
-/// Tracks the state of the network module
+/// Tracks the state of the network module.
+/// Dropping it (on completion or cancellation) makes the network module
+/// forget about the get process, see Action::CancelGetEntry.
 pub struct GetEntryFuture {
     context: Arc<Context>,
     key: GetEntryKey,
@@ -82,3 +84,42 @@ impl Future for GetEntryFuture {
         }
     }
 }
+
+impl Drop for GetEntryFuture {
+    fn drop(&mut self) {
+        // Not using dispatch_action() since panicking in drop would abort.
+        if let Some(action_channel) = self.context.action_channel.as_ref() {
+            let action_wrapper = ActionWrapper::new(Action::CancelGetEntry(self.key.clone()));
+            let _ = action_channel.send(action_wrapper);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::{tests::test_context_with_channels, Observer};
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn dropping_get_entry_future_cancels_the_get() {
+        let (action_tx, action_rx) = sync_channel::<ActionWrapper>(10);
+        let (observer_tx, _observer_rx) = sync_channel::<Observer>(10);
+        let context = test_context_with_channels("alice", &action_tx, &observer_tx, None);
+        let key = GetEntryKey {
+            address: Address::from("QmTest"),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        };
+
+        let future = GetEntryFuture {
+            context,
+            key: key.clone(),
+        };
+        drop(future);
+
+        assert_eq!(
+            *action_rx.try_recv().unwrap().action(),
+            Action::CancelGetEntry(key)
+        );
+    }
+}
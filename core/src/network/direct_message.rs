@@ -33,3 +33,14 @@ pub enum DirectMessage {
     /// "I can't"
     ValidationPackage(Option<ValidationPackage>),
 }
+
+impl DirectMessage {
+    /// name of the message variant, without any of its content
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DirectMessage::Custom(_) => "Custom",
+            DirectMessage::RequestValidationPackage(_) => "RequestValidationPackage",
+            DirectMessage::ValidationPackage(_) => "ValidationPackage",
+        }
+    }
+}
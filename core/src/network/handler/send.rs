@@ -59,14 +59,27 @@ pub fn handle_send_message_result(message_data: MessageData, context: Arc<Contex
     let response: DirectMessage =
         serde_json::from_str(&serde_json::to_string(&message_data.content).unwrap()).unwrap();
 
-    let initial_message = context
-        .state()
-        .unwrap()
-        .network()
-        .as_ref()
-        .direct_message_connections
-        .get(&message_data.request_id)
-        .cloned();
+    let network_state = context.state().unwrap().network();
+    if network_state.prune_on_read.is_some() {
+        let action_wrapper = ActionWrapper::new(Action::PruneDirectConnections);
+        dispatch_action(context.action_channel(), action_wrapper.clone());
+    }
+    // An expired connection is treated as gone even before the prune got reduced:
+    let initial_message = if network_state.has_direct_connection(&message_data.request_id) {
+        network_state
+            .direct_message_connections
+            .get(&message_data.request_id)
+            .cloned()
+    } else {
+        None
+    };
+    if initial_message.is_some() {
+        // a response is activity on the connection, even if it does not resolve it
+        let action_wrapper = ActionWrapper::new(Action::TouchDirectConnection(
+            message_data.request_id.clone(),
+        ));
+        dispatch_action(context.action_channel(), action_wrapper.clone());
+    }
 
     match response {
         DirectMessage::Custom(custom_direct_message) => {
@@ -75,14 +88,18 @@ pub fn handle_send_message_result(message_data: MessageData, context: Arc<Contex
                 return;
             }
 
+            let outcome = custom_direct_message.payload.clone().map(|_| ());
             let action_wrapper = ActionWrapper::new(Action::HandleCustomSendResponse((
                 message_data.request_id.clone(),
                 custom_direct_message.payload,
             )));
             dispatch_action(context.action_channel(), action_wrapper.clone());
 
-            let action_wrapper =
-                ActionWrapper::new(Action::ResolveDirectConnection(message_data.request_id));
+            let action_wrapper = ActionWrapper::new(Action::ResolveDirectConnectionFrom((
+                message_data.request_id,
+                Address::from(message_data.from_agent_id),
+                outcome,
+            )));
             dispatch_action(context.action_channel(), action_wrapper.clone());
         }
         DirectMessage::RequestValidationPackage(_) => context.log(
@@ -103,8 +120,11 @@ pub fn handle_send_message_result(message_data: MessageData, context: Arc<Contex
             )));
             dispatch_action(context.action_channel(), action_wrapper.clone());
 
-            let action_wrapper =
-                ActionWrapper::new(Action::ResolveDirectConnection(message_data.request_id));
+            let action_wrapper = ActionWrapper::new(Action::ResolveDirectConnectionFrom((
+                message_data.request_id,
+                Address::from(message_data.from_agent_id),
+                Ok(()),
+            )));
             dispatch_action(context.action_channel(), action_wrapper.clone());
         }
     };
@@ -1,4 +1,5 @@
 pub mod actions;
+pub mod clock;
 pub mod direct_message;
 pub mod entry_with_header;
 pub mod handler;
@@ -65,6 +65,17 @@ pub fn reduce_get_entry_timeout(
     }
 }
 
+pub fn reduce_cancel_get_entry(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let key = unwrap_to!(action => crate::action::Action::CancelGetEntry);
+
+    network_state.get_entry_with_meta_results.remove(key);
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -104,6 +115,35 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn reduce_cancel_get_entry_removes_pending_get() {
+        let context = test_context("alice", None);
+        let store = test_store(context.clone());
+
+        let key = GetEntryKey {
+            address: test_entry().address(),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        };
+        let store = store.reduce(ActionWrapper::new(Action::FetchEntry(key.clone())));
+        assert!(store
+            .network()
+            .get_entry_with_meta_results
+            .contains_key(&key));
+
+        let store = store.reduce(ActionWrapper::new(Action::CancelGetEntry(key.clone())));
+        assert!(!store
+            .network()
+            .get_entry_with_meta_results
+            .contains_key(&key));
+
+        // a late timeout does not bring it back
+        let store = store.reduce(ActionWrapper::new(Action::GetEntryTimeout(key.clone())));
+        assert!(!store
+            .network()
+            .get_entry_with_meta_results
+            .contains_key(&key));
+    }
+
     #[test]
     // This test needs to be refactored.
     // It is non-deterministically failing with "sending on a closed channel" originating form
@@ -42,7 +42,10 @@ pub fn reduce_handle_get_result(
         id: dht_data.request_id.clone(),
     };
 
-    network_state
-        .get_entry_with_meta_results
-        .insert(key, Some(result));
+    // Only store results somebody is still waiting for, see Action::CancelGetEntry
+    if network_state.get_entry_with_meta_results.contains_key(&key) {
+        network_state
+            .get_entry_with_meta_results
+            .insert(key, Some(result));
+    }
 }
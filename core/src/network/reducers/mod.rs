@@ -17,7 +17,7 @@ use crate::{
     network::{
         direct_message::DirectMessage,
         reducers::{
-            get_entry::{reduce_get_entry, reduce_get_entry_timeout},
+            get_entry::{reduce_cancel_get_entry, reduce_get_entry, reduce_get_entry_timeout},
             get_links::{reduce_get_links, reduce_get_links_timeout},
             get_validation_package::reduce_get_validation_package,
             handle_custom_send_response::reduce_handle_custom_send_response,
@@ -26,7 +26,10 @@ use crate::{
             handle_get_validation_package::reduce_handle_get_validation_package,
             init::reduce_init,
             publish::reduce_publish,
-            resolve_direct_connection::reduce_resolve_direct_connection,
+            resolve_direct_connection::{
+                reduce_prune_direct_connections, reduce_resolve_direct_connection,
+                reduce_resolve_direct_connection_from, reduce_touch_direct_connection,
+            },
             respond_get::reduce_respond_fetch_data,
             respond_get_links::reduce_respond_get_links,
             send_direct_message::{reduce_send_direct_message, reduce_send_direct_message_timeout},
@@ -46,6 +49,7 @@ use std::sync::Arc;
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
     match action_wrapper.action() {
+        Action::CancelGetEntry(_) => Some(reduce_cancel_get_entry),
         Action::FetchEntry(_) => Some(reduce_get_entry),
         Action::GetEntryTimeout(_) => Some(reduce_get_entry_timeout),
         Action::GetLinks(_) => Some(reduce_get_links),
@@ -56,16 +60,35 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
         Action::HandleGetLinksResult(_) => Some(reduce_handle_get_links_result),
         Action::HandleGetValidationPackage(_) => Some(reduce_handle_get_validation_package),
         Action::InitNetwork(_) => Some(reduce_init),
+        Action::PruneDirectConnections => Some(reduce_prune_direct_connections),
         Action::Publish(_) => Some(reduce_publish),
         Action::ResolveDirectConnection(_) => Some(reduce_resolve_direct_connection),
+        Action::ResolveDirectConnectionFrom(_) => Some(reduce_resolve_direct_connection_from),
         Action::RespondFetch(_) => Some(reduce_respond_fetch_data),
         Action::RespondGetLinks(_) => Some(reduce_respond_get_links),
         Action::SendDirectMessage(_) => Some(reduce_send_direct_message),
         Action::SendDirectMessageTimeout(_) => Some(reduce_send_direct_message_timeout),
+        Action::TouchDirectConnection(_) => Some(reduce_touch_direct_connection),
         _ => None,
     }
 }
 
+/// Actions whose reducers open, touch or close direct message connections (or initialize
+/// the network they belong to), i.e. the only ones that can break what
+/// NetworkState::validate checks. Validating after every other action would be wasted work.
+fn changes_direct_connections(action: &Action) -> bool {
+    match action {
+        Action::GetValidationPackage(_)
+        | Action::InitNetwork(_)
+        | Action::PruneDirectConnections
+        | Action::ResolveDirectConnection(_)
+        | Action::ResolveDirectConnectionFrom(_)
+        | Action::SendDirectMessage(_)
+        | Action::TouchDirectConnection(_) => true,
+        _ => false,
+    }
+}
+
 pub fn reduce(
     old_state: Arc<NetworkState>,
     root_state: &State,
@@ -76,6 +99,18 @@ pub fn reduce(
         Some(f) => {
             let mut new_state: NetworkState = (*old_state).clone();
             f(&mut new_state, &root_state, &action_wrapper);
+            if changes_direct_connections(action_wrapper.action()) {
+                let validation = new_state.validate();
+                debug_assert!(
+                    validation.is_ok(),
+                    "inconsistent NetworkState after {:?}: {:?}",
+                    action_wrapper.action(),
+                    validation
+                );
+                if validation.is_err() {
+                    new_state.drop_inconsistent_direct_connections();
+                }
+            }
             Arc::new(new_state)
         }
         None => old_state,
@@ -124,7 +159,7 @@ pub fn send_message(
 
     let _ = send(network_state, JsonProtocol::SendMessage(data))?;
 
-    network_state.direct_message_connections.insert(id, message);
+    network_state.open_direct_connection(id, to_agent_id.clone(), message);
 
     Ok(())
 }
@@ -1,4 +1,17 @@
-use crate::{action::ActionWrapper, network::state::NetworkState, state::State};
+use crate::{
+    action::ActionWrapper,
+    network::{direct_message::DirectMessage, state::NetworkState},
+    state::State,
+};
+use holochain_core_types::error::HolochainError;
+
+fn resolve_connection(network_state: &mut NetworkState, id: &String, outcome: &Result<(), String>) {
+    network_state.close_direct_connection(id);
+
+    if let Err(reason) = outcome {
+        network_state.record_connection_failure(id.clone(), reason.clone());
+    }
+}
 
 pub fn reduce_resolve_direct_connection(
     network_state: &mut NetworkState,
@@ -6,7 +19,284 @@ pub fn reduce_resolve_direct_connection(
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let id = unwrap_to!(action => crate::action::Action::ResolveDirectConnection);
+    let (id, outcome) = unwrap_to!(action => crate::action::Action::ResolveDirectConnection);
+    resolve_connection(network_state, id, outcome);
+}
+
+pub fn reduce_resolve_direct_connection_from(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (id, peer, outcome) =
+        unwrap_to!(action => crate::action::Action::ResolveDirectConnectionFrom);
+
+    let expected_peer = network_state
+        .direct_message_meta
+        .get(id)
+        .map(|meta| meta.peer.clone());
+    if expected_peer.as_ref() == Some(peer) {
+        resolve_connection(network_state, id, outcome);
+    } else {
+        network_state.record_peer_mismatch(id.clone(), peer.clone());
+    }
+}
+
+pub fn reduce_touch_direct_connection(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let id = unwrap_to!(action => crate::action::Action::TouchDirectConnection);
+    network_state.touch_direct_connection(id);
+}
+
+pub fn reduce_prune_direct_connections(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    _action_wrapper: &ActionWrapper,
+) {
+    for id in network_state.expired_direct_connection_ids() {
+        let is_custom = match network_state.direct_message_connections.get(&id) {
+            Some(DirectMessage::Custom(_)) => true,
+            _ => false,
+        };
+        resolve_connection(network_state, &id, &Err("expired".to_string()));
+        if is_custom
+            && network_state
+                .custom_direct_message_replys
+                .get(&id)
+                .is_none()
+        {
+            network_state
+                .custom_direct_message_replys
+                .insert(id, Err(HolochainError::Timeout));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::Action,
+        instance::tests::test_context,
+        network::{
+            clock::{Clock, MockClock},
+            direct_message::CustomDirectMessage,
+            reducers::reduce,
+            state::{MAX_RECENTLY_RESOLVED, MAX_RECENT_CONNECTION_FAILURES},
+        },
+        state::test_store,
+    };
+    use holochain_core_types::cas::content::Address;
+    use std::{sync::Arc, time::Duration};
+
+    fn resolve(network_state: &mut NetworkState, id: &str, outcome: Result<(), String>) {
+        let root_state = test_store(test_context("alice", None));
+        let action_wrapper =
+            ActionWrapper::new(Action::ResolveDirectConnection((id.to_string(), outcome)));
+        reduce_resolve_direct_connection(network_state, &root_state, &action_wrapper);
+    }
+
+    #[test]
+    fn test_resolve_direct_connection_records_failures() {
+        let mut network_state = NetworkState::new();
+        for id in &["ok", "failed"] {
+            network_state.direct_message_connections.insert(
+                id.to_string(),
+                DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+            );
+        }
+
+        resolve(&mut network_state, "ok", Ok(()));
+        assert!(network_state.recent_connection_failures.is_empty());
+
+        resolve(
+            &mut network_state,
+            "failed",
+            Err("receive failed".to_string()),
+        );
+        assert_eq!(
+            network_state.recent_connection_failures,
+            vec![("failed".to_string(), "receive failed".to_string())]
+        );
+        assert!(network_state.direct_message_connections.is_empty());
+    }
+
+    #[test]
+    fn test_recent_connection_failures_are_bounded() {
+        let mut network_state = NetworkState::new();
+        for i in 0..MAX_RECENT_CONNECTION_FAILURES + 5 {
+            network_state.record_connection_failure(i.to_string(), "error".to_string());
+        }
+        assert_eq!(
+            network_state.recent_connection_failures.len(),
+            MAX_RECENT_CONNECTION_FAILURES
+        );
+        assert_eq!(network_state.recent_connection_failures[0].0, "5");
+    }
+
+    #[test]
+    fn test_resolve_direct_connection_from_other_peer_keeps_connection() {
+        let mut network_state = NetworkState::new();
+        let id = "conn".to_string();
+        network_state.open_direct_connection(
+            id.clone(),
+            Address::from("bob"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        let root_state = test_store(test_context("alice", None));
+        let resolve_from = |network_state: &mut NetworkState, peer: &str| {
+            let action_wrapper = ActionWrapper::new(Action::ResolveDirectConnectionFrom((
+                id.clone(),
+                Address::from(peer),
+                Ok(()),
+            )));
+            reduce_resolve_direct_connection_from(network_state, &root_state, &action_wrapper);
+        };
+
+        resolve_from(&mut network_state, "mallory");
+        assert!(network_state.direct_message_connections.contains_key(&id));
+        assert_eq!(
+            network_state.recent_peer_mismatches,
+            vec![(id.clone(), Address::from("mallory"))]
+        );
+
+        resolve_from(&mut network_state, "bob");
+        assert!(network_state.direct_message_connections.is_empty());
+        assert!(network_state.direct_message_meta.is_empty());
+        assert_eq!(network_state.recent_peer_mismatches.len(), 1);
+    }
+
+    #[test]
+    fn test_resolved_connection_meta_is_kept() {
+        let mut network_state = NetworkState::new();
+        network_state.open_direct_connection(
+            "conn".to_string(),
+            Address::from("bob"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        assert!(network_state.recently_resolved.is_empty());
+
+        resolve(&mut network_state, "conn", Ok(()));
+
+        assert_eq!(network_state.recently_resolved.len(), 1);
+        let (id, meta) = &network_state.recently_resolved[0];
+        assert_eq!(id, "conn");
+        assert_eq!(meta.peer, Address::from("bob"));
+        assert!(meta.open_duration().is_some());
+    }
+
+    #[test]
+    fn test_recently_resolved_is_bounded() {
+        let mut network_state = NetworkState::new();
+        for i in 0..MAX_RECENTLY_RESOLVED + 5 {
+            network_state.open_direct_connection(
+                i.to_string(),
+                Address::from("bob"),
+                DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+            );
+            resolve(&mut network_state, &i.to_string(), Ok(()));
+        }
+        assert_eq!(network_state.recently_resolved.len(), MAX_RECENTLY_RESOLVED);
+        assert_eq!(network_state.recently_resolved[0].0, "5");
+    }
+
+    #[test]
+    fn test_prune_direct_connections_times_out_expired_connections() {
+        let clock = MockClock::new();
+        let mut network_state = NetworkState::new();
+        network_state.clock = Arc::new(clock.clone());
+        network_state.prune_on_read = Some(Duration::from_secs(30));
+        network_state.dna_address = Some(Address::from("QmDna"));
+        network_state.agent_id = Some("alice".to_string());
+        network_state.open_direct_connection(
+            "custom".to_string(),
+            Address::from("bob"),
+            DirectMessage::Custom(CustomDirectMessage {
+                zome: "zome".to_string(),
+                payload: Ok("payload".to_string()),
+            }),
+        );
+        network_state.open_direct_connection(
+            "package".to_string(),
+            Address::from("bob"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        clock.advance(Duration::from_secs(20));
+        network_state.open_direct_connection(
+            "fresh".to_string(),
+            Address::from("bob"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        clock.advance(Duration::from_secs(10));
+
+        let root_state = test_store(test_context("alice", None));
+        let action_wrapper = ActionWrapper::new(Action::PruneDirectConnections);
+        let network_state = reduce(Arc::new(network_state), &root_state, &action_wrapper);
+
+        assert_eq!(
+            network_state.open_direct_connection_ids(),
+            vec!["fresh".to_string()]
+        );
+        assert!(network_state
+            .direct_connection(&"custom".to_string())
+            .is_none());
+        assert!(network_state
+            .direct_connection(&"package".to_string())
+            .is_none());
+        assert_eq!(
+            network_state.custom_direct_message_replys.get("custom"),
+            Some(&Err(HolochainError::Timeout))
+        );
+        assert!(network_state
+            .custom_direct_message_replys
+            .get("package")
+            .is_none());
+        assert_eq!(
+            network_state.recent_connection_failures,
+            vec![
+                ("custom".to_string(), "expired".to_string()),
+                ("package".to_string(), "expired".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_touch_direct_connection_prevents_pruning() {
+        let clock = MockClock::new();
+        let mut network_state = NetworkState::new();
+        network_state.clock = Arc::new(clock.clone());
+        network_state.prune_on_read = Some(Duration::from_secs(30));
+        let id = "conn".to_string();
+        network_state.open_direct_connection(
+            id.clone(),
+            Address::from("bob"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        let root_state = test_store(test_context("alice", None));
+        let touch = ActionWrapper::new(Action::TouchDirectConnection(id.clone()));
+        let prune = ActionWrapper::new(Action::PruneDirectConnections);
+
+        clock.advance(Duration::from_secs(20));
+        reduce_touch_direct_connection(&mut network_state, &root_state, &touch);
+        let meta = network_state.direct_connection(&id).unwrap();
+        assert_eq!(meta.last_activity, clock.now());
+        assert_eq!(meta.opened_at + Duration::from_secs(20), meta.last_activity);
+
+        clock.advance(Duration::from_secs(20));
+        reduce_prune_direct_connections(&mut network_state, &root_state, &prune);
+        assert!(network_state.has_direct_connection(&id));
+
+        clock.advance(Duration::from_secs(10));
+        reduce_prune_direct_connections(&mut network_state, &root_state, &prune);
+        assert!(!network_state.has_direct_connection(&id));
 
-    network_state.direct_message_connections.remove(id);
+        // touching a connection that is gone does nothing
+        reduce_touch_direct_connection(&mut network_state, &root_state, &touch);
+        assert!(network_state.direct_connection(&id).is_none());
+    }
 }
@@ -26,9 +26,11 @@ fn inner(
     let protocol_object = if direct_message_data.is_response {
         JsonProtocol::HandleSendMessageResult(data)
     } else {
-        network_state
-            .direct_message_connections
-            .insert(data.request_id.clone(), direct_message_data.message.clone());
+        network_state.open_direct_connection(
+            data.request_id.clone(),
+            direct_message_data.address.clone(),
+            direct_message_data.message.clone(),
+        );
         JsonProtocol::SendMessage(data)
     };
 
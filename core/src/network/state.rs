@@ -1,21 +1,69 @@
 use crate::{
     action::{ActionWrapper, GetEntryKey, GetLinksKey},
-    network::{actions::ActionResponse, direct_message::DirectMessage},
+    network::{
+        actions::ActionResponse,
+        clock::{Clock, SystemClock},
+        direct_message::DirectMessage,
+    },
 };
 use boolinator::*;
 use holochain_core_types::{
-    cas::content::Address, entry::EntryWithMetaAndHeader, error::HolochainError,
+    cas::content::Address, entry::EntryWithMetaAndHeader, error::HolochainError, json::JsonString,
     validation::ValidationPackage,
 };
+use holochain_dpki::utils::address_of_id;
 use holochain_net::p2p_network::P2pNetwork;
 use snowflake;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 type Actions = HashMap<ActionWrapper, ActionResponse>;
 
+/// How many failed direct connections are kept in NetworkState::recent_connection_failures
+pub const MAX_RECENT_CONNECTION_FAILURES: usize = 20;
+
+/// How many responses from unexpected peers are kept in NetworkState::recent_peer_mismatches
+pub const MAX_RECENT_PEER_MISMATCHES: usize = 20;
+
+/// How many resolved direct connections are kept in NetworkState::recently_resolved
+pub const MAX_RECENTLY_RESOLVED: usize = 20;
+
+/// Book-keeping of a direct message connection besides the message it was opened for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectConnectionMeta {
+    /// the agent the connection was opened to
+    pub peer: Address,
+    /// the DHT address of the peer's neighborhood (see hc_dpki::utils::address_of_id),
+    /// None if the peer is not a valid agent id
+    pub target_address: Option<Address>,
+    pub opened_at: Instant,
+    /// when the connection was opened or last saw activity, expiry is based on this
+    pub last_activity: Instant,
+    /// None as long as the connection is open
+    pub resolved_at: Option<Instant>,
+}
+
+impl DirectConnectionMeta {
+    pub fn new(peer: Address, opened_at: Instant) -> Self {
+        DirectConnectionMeta {
+            target_address: address_of_id(&String::from(peer.clone())).ok(),
+            peer,
+            opened_at,
+            last_activity: opened_at,
+            resolved_at: None,
+        }
+    }
+
+    /// How long the connection was open, None if it is not resolved yet.
+    pub fn open_duration(&self) -> Option<Duration> {
+        self.resolved_at
+            .map(|resolved_at| resolved_at.duration_since(self.opened_at))
+    }
+}
+
 /// This represents the state of a get_entry network process:
 /// None: process started, but no response yet from the network
 /// Some(Err(_)): there was a problem at some point
@@ -66,8 +114,34 @@ pub struct NetworkState {
     /// Entries get removed when we receive an answer through Action::ResolveDirectConnection.
     pub direct_message_connections: HashMap<String, DirectMessage>,
 
+    /// Book-keeping of each open direct message connection, by connection id.
+    /// Only a response from the connection's peer resolves it,
+    /// see Action::ResolveDirectConnectionFrom.
+    pub direct_message_meta: HashMap<String, DirectConnectionMeta>,
+
     pub custom_direct_message_replys: HashMap<String, Result<String, HolochainError>>,
 
+    /// The (id, reason) of the last direct connections that got resolved with an error,
+    /// oldest first, bounded by MAX_RECENT_CONNECTION_FAILURES.
+    pub recent_connection_failures: Vec<(String, String)>,
+
+    /// The (id, responding agent) of the last responses that came from another agent
+    /// than the connection was opened to, oldest first, bounded by MAX_RECENT_PEER_MISMATCHES.
+    pub recent_peer_mismatches: Vec<(String, Address)>,
+
+    /// The (id, book-keeping) of the last resolved direct connections, oldest first,
+    /// bounded by MAX_RECENTLY_RESOLVED. Lets observers look at a connection
+    /// (e.g. how long it was open) after it got removed.
+    pub recently_resolved: VecDeque<(String, DirectConnectionMeta)>,
+
+    /// If set, direct connections without activity for longer than this count as expired:
+    /// has_direct_connection and open_direct_connection_ids leave them out and
+    /// Action::PruneDirectConnections removes them. Off by default.
+    pub prune_on_read: Option<Duration>,
+
+    /// Where the direct connection book-keeping gets the current time from
+    pub clock: Arc<dyn Clock>,
+
     id: snowflake::ProcessUniqueId,
 }
 
@@ -89,7 +163,13 @@ impl NetworkState {
             get_links_results: HashMap::new(),
             get_validation_package_results: HashMap::new(),
             direct_message_connections: HashMap::new(),
+            direct_message_meta: HashMap::new(),
             custom_direct_message_replys: HashMap::new(),
+            recent_connection_failures: Vec::new(),
+            recent_peer_mismatches: Vec::new(),
+            recently_resolved: VecDeque::new(),
+            prune_on_read: None,
+            clock: Arc::new(SystemClock),
 
             id: snowflake::ProcessUniqueId::new(),
         }
@@ -104,4 +184,431 @@ impl NetworkState {
             HolochainError::ErrorGeneric("Network not initialized".to_string()),
         )
     }
+
+    /// Logs a direct message connection to the given peer as open, i.e. waiting for a response.
+    pub fn open_direct_connection(&mut self, id: String, peer: Address, message: DirectMessage) {
+        self.direct_message_meta.insert(
+            id.clone(),
+            DirectConnectionMeta::new(peer, self.clock.now()),
+        );
+        self.direct_message_connections.insert(id, message);
+    }
+
+    /// Book-keeping of the given direct message connection while it is open.
+    pub fn direct_connection(&self, id: &String) -> Option<&DirectConnectionMeta> {
+        self.direct_message_meta.get(id)
+    }
+
+    /// Whether the given direct message connection is open and not expired.
+    pub fn has_direct_connection(&self, id: &String) -> bool {
+        self.direct_message_connections.contains_key(id) && !self.is_expired(id)
+    }
+
+    /// Ids of all open direct message connections that are not expired.
+    pub fn open_direct_connection_ids(&self) -> Vec<String> {
+        self.direct_message_connections
+            .keys()
+            .filter(|id| !self.is_expired(id))
+            .cloned()
+            .collect()
+    }
+
+    /// Ids of the direct message connections without activity for longer than
+    /// prune_on_read, sorted. Always empty if prune_on_read is not set.
+    pub fn expired_direct_connection_ids(&self) -> Vec<String> {
+        let mut expired: Vec<String> = self
+            .direct_message_meta
+            .keys()
+            .filter(|id| self.is_expired(id))
+            .cloned()
+            .collect();
+        expired.sort();
+        expired
+    }
+
+    /// Sets the last activity of the given direct message connection to now,
+    /// no-op if it is not open.
+    pub fn touch_direct_connection(&mut self, id: &String) {
+        let now = self.clock.now();
+        if let Some(meta) = self.direct_message_meta.get_mut(id) {
+            meta.last_activity = now;
+        }
+    }
+
+    fn is_expired(&self, id: &String) -> bool {
+        match (self.prune_on_read, self.direct_message_meta.get(id)) {
+            (Some(ttl), Some(meta)) => self.clock.now().duration_since(meta.last_activity) >= ttl,
+            _ => false,
+        }
+    }
+
+    /// Forgets about the given direct message connection, keeping its book-keeping
+    /// in recently_resolved, dropping the oldest entry there once MAX_RECENTLY_RESOLVED
+    /// are stored.
+    pub fn close_direct_connection(&mut self, id: &String) {
+        self.direct_message_connections.remove(id);
+        if let Some(mut meta) = self.direct_message_meta.remove(id) {
+            meta.resolved_at = Some(self.clock.now());
+            if self.recently_resolved.len() >= MAX_RECENTLY_RESOLVED {
+                self.recently_resolved.pop_front();
+            }
+            self.recently_resolved.push_back((id.clone(), meta));
+        }
+    }
+
+    /// Keeps the reason of a failed direct connection, dropping the oldest one
+    /// once MAX_RECENT_CONNECTION_FAILURES are stored.
+    pub fn record_connection_failure(&mut self, id: String, reason: String) {
+        if self.recent_connection_failures.len() >= MAX_RECENT_CONNECTION_FAILURES {
+            self.recent_connection_failures.remove(0);
+        }
+        self.recent_connection_failures.push((id, reason));
+    }
+
+    /// Keeps the id of a connection that got a response from an unexpected agent,
+    /// dropping the oldest one once MAX_RECENT_PEER_MISMATCHES are stored.
+    pub fn record_peer_mismatch(&mut self, id: String, peer: Address) {
+        if self.recent_peer_mismatches.len() >= MAX_RECENT_PEER_MISMATCHES {
+            self.recent_peer_mismatches.remove(0);
+        }
+        self.recent_peer_mismatches.push((id, peer));
+    }
+
+    /// Serializable overview of the direct connections, e.g. to attach to a support ticket:
+    /// the open ones with the kind of message they were opened for, followed by the
+    /// recently failed ones. Neither message payloads nor failure reasons are included
+    /// since both can hold app data.
+    /// Times are given as milliseconds elapsed until now (opened_ms_ago, last_activity_ms_ago,
+    /// resolved_ms_ago), null where not known.
+    pub fn direct_connections_snapshot(&self) -> JsonString {
+        let now = self.clock.now();
+        let ms_ago = |instant: Instant| now.duration_since(instant).as_millis() as u64;
+        let times = |meta: Option<&DirectConnectionMeta>| {
+            (
+                meta.map(|meta| ms_ago(meta.opened_at)),
+                meta.map(|meta| ms_ago(meta.last_activity)),
+                meta.and_then(|meta| meta.resolved_at)
+                    .map(|resolved_at| ms_ago(resolved_at)),
+            )
+        };
+
+        let mut open_ids: Vec<&String> = self.direct_message_connections.keys().collect();
+        open_ids.sort();
+        let connections: Vec<serde_json::Value> = open_ids
+            .into_iter()
+            .map(|id| {
+                let (opened, last_activity, resolved) = times(self.direct_message_meta.get(id));
+                serde_json::json!({
+                    "id": id,
+                    "state": "open",
+                    "kind": self.direct_message_connections[id].kind(),
+                    "opened_ms_ago": opened,
+                    "last_activity_ms_ago": last_activity,
+                    "resolved_ms_ago": resolved,
+                })
+            })
+            .chain(self.recent_connection_failures.iter().map(|(id, _)| {
+                let meta = self
+                    .recently_resolved
+                    .iter()
+                    .rev()
+                    .find(|(resolved_id, _)| resolved_id == id)
+                    .map(|(_, meta)| meta);
+                let (opened, last_activity, resolved) = times(meta);
+                serde_json::json!({
+                    "id": id,
+                    "state": "failed",
+                    "opened_ms_ago": opened,
+                    "last_activity_ms_ago": last_activity,
+                    "resolved_ms_ago": resolved,
+                })
+            }))
+            .collect();
+        JsonString::from(serde_json::json!({ "direct_connections": connections }))
+    }
+
+    /// Checks invariants of the state that reducers rely on.
+    /// Returns a description of every violated invariant, so a corrupt state
+    /// (e.g. after a bad restore) does not go unnoticed.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.direct_message_connections.contains_key("") {
+            problems.push("direct message connection with empty id".to_string());
+        }
+
+        if !self.direct_message_connections.is_empty()
+            && (self.dna_address.is_none() || self.agent_id.is_none())
+        {
+            problems.push(format!(
+                "{} open direct message connection(s) but network is not initialized",
+                self.direct_message_connections.len()
+            ));
+        }
+
+        let mut connection_ids: Vec<&String> = self.direct_message_connections.keys().collect();
+        connection_ids.sort();
+        for id in connection_ids {
+            if !self.direct_message_meta.contains_key(id) {
+                problems.push(format!("direct message connection {} without meta", id));
+            }
+        }
+
+        let mut meta_ids: Vec<&String> = self.direct_message_meta.keys().collect();
+        meta_ids.sort();
+        for id in meta_ids {
+            if !self.direct_message_connections.contains_key(id) {
+                problems.push(format!("direct connection meta {} without connection", id));
+            }
+            if self.direct_message_meta[id].resolved_at.is_some() {
+                problems.push(format!("open direct connection {} is resolved", id));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Drops every direct message connection that violates one of the invariants
+    /// checked by validate, recording it as failed, so that a corrupt state does not
+    /// leave callers waiting on a connection that can never be resolved.
+    pub fn drop_inconsistent_direct_connections(&mut self) {
+        let mut inconsistent: Vec<String> = self
+            .direct_message_connections
+            .keys()
+            .filter(|id| id.is_empty() || !self.direct_message_meta.contains_key(*id))
+            .chain(self.direct_message_meta.iter().filter_map(|(id, meta)| {
+                if id.is_empty()
+                    || meta.resolved_at.is_some()
+                    || !self.direct_message_connections.contains_key(id)
+                {
+                    Some(id)
+                } else {
+                    None
+                }
+            }))
+            .cloned()
+            .collect();
+        inconsistent.sort();
+        inconsistent.dedup();
+        for id in inconsistent {
+            self.direct_message_connections.remove(&id);
+            self.direct_message_meta.remove(&id);
+            self.record_connection_failure(id, "inconsistent state".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::network::{
+        clock::MockClock,
+        direct_message::{CustomDirectMessage, DirectMessage},
+    };
+    use holochain_core_types::agent::GOOD_ID;
+
+    #[test]
+    fn test_direct_connections_snapshot_redacts_payloads() {
+        let clock = MockClock::new();
+        let mut state = NetworkState::new();
+        state.clock = Arc::new(clock.clone());
+        state.open_direct_connection(
+            "b-id".to_string(),
+            Address::from("peer"),
+            DirectMessage::Custom(CustomDirectMessage {
+                zome: "zome".to_string(),
+                payload: Ok("secret payload".to_string()),
+            }),
+        );
+        state.open_direct_connection(
+            "c-id".to_string(),
+            Address::from("peer"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        clock.advance(Duration::from_millis(500));
+        state.open_direct_connection(
+            "a-id".to_string(),
+            Address::from("peer"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        clock.advance(Duration::from_millis(500));
+        state.touch_direct_connection(&"b-id".to_string());
+        state.close_direct_connection(&"c-id".to_string());
+        state.record_connection_failure("c-id".to_string(), "secret reason".to_string());
+        clock.advance(Duration::from_millis(250));
+
+        let snapshot = String::from(state.direct_connections_snapshot());
+        assert_eq!(
+            snapshot,
+            "{\"direct_connections\":[\
+             {\"id\":\"a-id\",\"state\":\"open\",\"kind\":\"RequestValidationPackage\",\
+             \"opened_ms_ago\":750,\"last_activity_ms_ago\":750,\"resolved_ms_ago\":null},\
+             {\"id\":\"b-id\",\"state\":\"open\",\"kind\":\"Custom\",\
+             \"opened_ms_ago\":1250,\"last_activity_ms_ago\":250,\"resolved_ms_ago\":null},\
+             {\"id\":\"c-id\",\"state\":\"failed\",\
+             \"opened_ms_ago\":1250,\"last_activity_ms_ago\":1250,\"resolved_ms_ago\":250}]}"
+        );
+        assert!(!snapshot.contains("secret"));
+    }
+
+    #[test]
+    fn test_direct_connection_targets_the_peers_dht_address() {
+        let mut state = NetworkState::new();
+        state.open_direct_connection(
+            "id".to_string(),
+            Address::from(GOOD_ID),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        let meta = state.direct_connection(&"id".to_string()).unwrap();
+        assert_eq!(meta.target_address, Some(address_of_id(GOOD_ID).unwrap()));
+
+        state.open_direct_connection(
+            "other-id".to_string(),
+            Address::from("not an agent id"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        let meta = state.direct_connection(&"other-id".to_string()).unwrap();
+        assert_eq!(meta.target_address, None);
+
+        state.close_direct_connection(&"id".to_string());
+        assert!(state.direct_connection(&"id".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_prune_on_read_hides_expired_connections() {
+        let clock = MockClock::new();
+        let mut state = NetworkState::new();
+        state.clock = Arc::new(clock.clone());
+        let stale = "stale".to_string();
+        state.open_direct_connection(
+            stale.clone(),
+            Address::from("peer"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        clock.advance(Duration::from_secs(60));
+        state.open_direct_connection(
+            "fresh".to_string(),
+            Address::from("peer"),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+
+        // nothing expires unless opted in:
+        assert!(state.has_direct_connection(&stale));
+        assert_eq!(state.open_direct_connection_ids().len(), 2);
+        assert!(state.expired_direct_connection_ids().is_empty());
+
+        state.prune_on_read = Some(Duration::from_secs(30));
+        assert!(!state.has_direct_connection(&stale));
+        assert_eq!(
+            state.open_direct_connection_ids(),
+            vec!["fresh".to_string()]
+        );
+        assert_eq!(state.expired_direct_connection_ids(), vec![stale.clone()]);
+        // reading leaves the removal to Action::PruneDirectConnections
+        assert!(state.direct_connection(&stale).is_some());
+    }
+
+    #[test]
+    fn test_direct_connection_times_with_mock_clock() {
+        let clock = MockClock::new();
+        let mut state = NetworkState::new();
+        state.clock = Arc::new(clock.clone());
+        state.prune_on_read = Some(Duration::from_secs(30));
+        for id in &["slow", "quick"] {
+            state.open_direct_connection(
+                id.to_string(),
+                Address::from("peer"),
+                DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+            );
+        }
+
+        clock.advance(Duration::from_secs(10));
+        state.close_direct_connection(&"quick".to_string());
+        let (_, quick) = state.recently_resolved.back().unwrap();
+        assert_eq!(quick.open_duration(), Some(Duration::from_secs(10)));
+
+        clock.advance(Duration::from_secs(19));
+        assert!(state.has_direct_connection(&"slow".to_string()));
+        clock.advance(Duration::from_secs(1));
+        assert!(!state.has_direct_connection(&"slow".to_string()));
+    }
+
+    #[test]
+    fn test_validate_reports_invalid_direct_message_connections() {
+        let mut state = NetworkState::new();
+        assert_eq!(state.validate(), Ok(()));
+
+        state.open_direct_connection(
+            String::new(),
+            Address::from(GOOD_ID),
+            DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+        );
+        let problems = state.validate().unwrap_err();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], "direct message connection with empty id");
+
+        state.dna_address = Some(Address::from("QmDna"));
+        state.agent_id = Some("agent".to_string());
+        assert_eq!(state.validate().unwrap_err().len(), 1);
+
+        let message = state.direct_message_connections.remove("").unwrap();
+        let meta = state.direct_message_meta.remove("").unwrap();
+        state
+            .direct_message_connections
+            .insert("some-id".to_string(), message);
+        state
+            .direct_message_meta
+            .insert("some-id".to_string(), meta);
+        assert_eq!(state.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_and_drops_inconsistent_direct_connections() {
+        let mut state = NetworkState::new();
+        state.dna_address = Some(Address::from("QmDna"));
+        state.agent_id = Some("agent".to_string());
+        for id in &["fine", "no-meta", "resolved"] {
+            state.open_direct_connection(
+                id.to_string(),
+                Address::from(GOOD_ID),
+                DirectMessage::RequestValidationPackage(Address::from("QmTest")),
+            );
+        }
+        let now = state.clock.now();
+        state.direct_message_meta.remove("no-meta");
+        state
+            .direct_message_meta
+            .get_mut("resolved")
+            .unwrap()
+            .resolved_at = Some(now);
+        state.direct_message_meta.insert(
+            "no-connection".to_string(),
+            DirectConnectionMeta::new(Address::from(GOOD_ID), now),
+        );
+
+        assert_eq!(
+            state.validate(),
+            Err(vec![
+                "direct message connection no-meta without meta".to_string(),
+                "direct connection meta no-connection without connection".to_string(),
+                "open direct connection resolved is resolved".to_string(),
+            ])
+        );
+
+        state.drop_inconsistent_direct_connections();
+        assert_eq!(state.validate(), Ok(()));
+        assert!(state.has_direct_connection(&"fine".to_string()));
+        assert_eq!(
+            state
+                .recent_connection_failures
+                .iter()
+                .map(|(id, _)| id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["no-connection", "no-meta", "resolved"]
+        );
+    }
 }
@@ -200,13 +200,14 @@ fn make_call_sig<J: Into<JsonString>>(
 }
 
 // temporary function to verify a mock signature of for a zome call cap request
+// a malformed signature or source just does not verify
 pub fn verify_call_sig<J: Into<JsonString>>(
     provenance: &Provenance,
     function: &str,
     parameters: J,
 ) -> bool {
     let what_was_signed = encode_call_data_for_signing(function, parameters);
-    provenance.verify(what_was_signed).unwrap()
+    provenance.verify(what_was_signed).unwrap_or(false)
 }
 
 /// creates a capability request for a zome call by signing the function name and parameters
@@ -360,6 +361,10 @@ pub mod tests {
         let bad_provenance = Provenance::new(context2.agent_id.address(), call_sig1);
 
         assert!(!verify_call_sig(&bad_provenance, "func", "{}"));
+
+        let malformed_provenance =
+            Provenance::new(context1.agent_id.address(), Signature::from("c2hvcnQ="));
+        assert!(!verify_call_sig(&malformed_provenance, "func", "{}"));
     }
 
     #[test]
@@ -1,4 +1,4 @@
-use crate::action::ActionWrapper;
+use crate::action::{Action, ActionWrapper};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use holochain_core_types::{error::HolochainError, json::JsonString};
 use serde::{Deserialize, Deserializer};
@@ -9,6 +9,31 @@ use std::thread;
 pub enum Signal {
     Trace(ActionWrapper),
     User(JsonString),
+    /// Number of signals of the given instance that could not be delivered
+    /// to a client so far, so clients know when to do a full resync.
+    SignalStats {
+        instance_id: String,
+        dropped: u64,
+    },
+}
+
+impl Signal {
+    /// True for trace signals of a direct connection's lifecycle:
+    /// it being opened, seeing activity, timing out or getting resolved.
+    pub fn is_direct_connection_event(&self) -> bool {
+        match self {
+            Signal::Trace(action_wrapper) => match action_wrapper.action() {
+                Action::SendDirectMessage(_)
+                | Action::SendDirectMessageTimeout(_)
+                | Action::ResolveDirectConnection(_)
+                | Action::ResolveDirectConnectionFrom(_)
+                | Action::TouchDirectConnection(_)
+                | Action::PruneDirectConnections => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Signal {
@@ -1,14 +1,24 @@
 use crate::{context::Context, network, nucleus};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
 use holochain_core_types::{chain_header::ChainHeader, time::Timeout};
 
 use holochain_core_types::{
-    cas::content::Address, crud_status::CrudStatus, entry::EntryWithMetaAndHeader,
+    cas::content::{Address, AddressableContent},
+    crud_status::CrudStatus,
+    entry::EntryWithMetaAndHeader,
     error::HolochainError,
 };
 use holochain_wasm_utils::api_serialization::get_entry::{
-    GetEntryArgs, GetEntryResult, StatusRequestKind,
+    EntryProof, GetEntryArgs, GetEntryResult, GetRetryOptions, StatusRequestKind,
+};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::sync::Arc;
 
 /// Get Entry workflow
 pub async fn get_entry_with_meta_workflow<'a>(
@@ -51,6 +61,76 @@ pub async fn get_entry_with_meta_workflow<'a>(
     }
 }
 
+/// Future that resolves once the given duration has passed.
+/// Used instead of thread::sleep so that waiting for a retry does not block
+/// the executor thread.
+struct Delay {
+    deadline: Instant,
+}
+
+impl Delay {
+    fn new(duration: Duration) -> Self {
+        Delay {
+            deadline: Instant::now() + duration,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            Poll::Ready(())
+        } else {
+            //
+            // TODO: connect the waker to a timer for performance reasons
+            // See: https://github.com/holochain/holochain-rust/issues/314
+            //
+            lw.wake();
+            Poll::Pending
+        }
+    }
+}
+
+/// Errors that might go away by trying again.
+/// Everything else (e.g. a failed validation) would fail the same way on every retry.
+fn is_transient(error: &HolochainError) -> bool {
+    match error {
+        HolochainError::Timeout => true,
+        _ => false,
+    }
+}
+
+/// Drives the given get attempt until it succeeds or the retries configured in
+/// retry_options are used up, waiting with exponential backoff in between.
+/// Only transient errors (i.e. network timeouts) trigger a retry, other errors and
+/// an Ok(None) (= entry does not exist) get returned right away.
+pub async fn retry_with_backoff<T, F, Fut>(
+    retry_options: GetRetryOptions,
+    mut attempt: F,
+) -> Result<T, HolochainError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, HolochainError>>,
+{
+    let mut failed_attempts = 0;
+    loop {
+        match await!(attempt()) {
+            Err(ref error) if is_transient(error) => {
+                match retry_options.delay_before_retry(failed_attempts) {
+                    Some(delay) => {
+                        failed_attempts += 1;
+                        await!(Delay::new(delay));
+                    }
+                    None => return Err(error.clone()),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
 /// Get GetEntryResult workflow
 pub async fn get_entry_result_workflow<'a>(
     context: &'a Arc<Context>,
@@ -59,17 +139,17 @@ pub async fn get_entry_result_workflow<'a>(
     // Setup
     let mut entry_result = GetEntryResult::new(args.options.status_request.clone(), None);
     let mut maybe_address = Some(args.address.clone());
+    let mut latest_address = None;
 
     // Accumulate entry history in a loop unless only request initial.
     while maybe_address.is_some() {
         let address = maybe_address.unwrap();
         maybe_address = None;
         // Try to get entry
-        let maybe_entry_with_meta_and_headers = await!(get_entry_with_meta_workflow(
-            context,
-            &address,
-            &args.options.timeout
-        ))?;
+        let maybe_entry_with_meta_and_headers =
+            await!(retry_with_backoff(args.options.retry.clone(), || {
+                get_entry_with_meta_workflow(context, &address, &args.options.timeout)
+            }))?;
 
         // Entry found
         if let Some(entry_with_meta_and_headers) = maybe_entry_with_meta_and_headers {
@@ -81,6 +161,18 @@ pub async fn get_entry_result_workflow<'a>(
                 }
             }
 
+            if args.options.verify_content {
+                entry_result.record_content_verification(
+                    entry_with_meta_and_headers.entry_with_meta.entry.address() == address,
+                );
+            }
+
+            let maybe_proof = if args.options.include_proof {
+                EntryProof::from_headers(&address, &entry_with_meta_and_headers.headers)
+            } else {
+                None
+            };
+
             // Add entry
             let headers: Vec<ChainHeader> = if args.options.headers {
                 entry_with_meta_and_headers.headers
@@ -88,6 +180,10 @@ pub async fn get_entry_result_workflow<'a>(
                 Vec::new()
             };
             entry_result.push(&entry_with_meta_and_headers.entry_with_meta, headers);
+            latest_address = Some(address.clone());
+            if args.options.include_proof {
+                entry_result.record_proof(maybe_proof);
+            }
 
             if args.options.status_request == StatusRequestKind::Initial {
                 break;
@@ -111,9 +207,128 @@ pub async fn get_entry_result_workflow<'a>(
         }
     }
 
+    if args.options.if_newer_than.is_some()
+        && args.options.status_request == StatusRequestKind::Latest
+        && entry_result.found()
+        && latest_address == args.options.if_newer_than
+    {
+        entry_result.mark_not_modified();
+    }
+
+    if args.options.size_only {
+        entry_result.strip_to_size();
+    } else if let Some(max_bytes) = args.options.max_bytes {
+        entry_result.limit_size(max_bytes);
+    }
+
     Ok(entry_result)
 }
 
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::nucleus::ribosome::api::sign::sign_one_time;
+    use futures::{executor::block_on, future};
+    use holochain_core_types::{
+        entry::{test_entry, test_entry_b},
+        signature::Provenance,
+        time::test_iso_8601,
+    };
+    use holochain_dpki::utils::Verify;
+    use std::cell::Cell;
+
+    #[test]
+    fn entry_proof_verifies_against_author() {
+        let entry = test_entry();
+        let signed = sign_one_time(vec![entry.address().to_string()]).unwrap();
+        let author = Address::from(signed.pub_key);
+        let header = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &[Provenance::new(
+                author.clone(),
+                signed.signatures[0].clone(),
+            )],
+            &None,
+            &None,
+            &None,
+            &test_iso_8601(),
+        );
+
+        let proof = EntryProof::from_headers(&entry.address(), &[header]).unwrap();
+        assert_eq!(proof.provenance.source(), author);
+        assert!(proof
+            .provenance
+            .verify(entry.address().to_string())
+            .unwrap());
+        assert!(!proof
+            .provenance
+            .verify(test_entry_b().address().to_string())
+            .unwrap());
+    }
+
+    #[test]
+    fn retry_with_backoff_recovers_from_transient_failure() {
+        let attempts = Cell::new(0);
+        let result = block_on(retry_with_backoff(
+            GetRetryOptions::new(2, Timeout::new(1)),
+            || {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() == 1 {
+                    future::ready(Err(HolochainError::Timeout))
+                } else {
+                    future::ready(Ok(Some(42)))
+                }
+            },
+        ));
+        assert_eq!(result, Ok(Some(42)));
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[test]
+    fn retry_with_backoff_gives_up_after_max_retries() {
+        let attempts = Cell::new(0);
+        let result: Result<Option<u32>, HolochainError> = block_on(retry_with_backoff(
+            GetRetryOptions::new(2, Timeout::new(1)),
+            || {
+                attempts.set(attempts.get() + 1);
+                future::ready(Err(HolochainError::Timeout))
+            },
+        ));
+        assert_eq!(result, Err(HolochainError::Timeout));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn retry_with_backoff_does_not_retry_not_found() {
+        let attempts = Cell::new(0);
+        let result: Result<Option<u32>, HolochainError> = block_on(retry_with_backoff(
+            GetRetryOptions::new(2, Timeout::new(1)),
+            || {
+                attempts.set(attempts.get() + 1);
+                future::ready(Ok(None))
+            },
+        ));
+        assert_eq!(result, Ok(None));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn retry_with_backoff_does_not_retry_non_transient_error() {
+        let attempts = Cell::new(0);
+        let error = HolochainError::ErrorGeneric("validation failed".to_string());
+        let result: Result<Option<u32>, HolochainError> = block_on(retry_with_backoff(
+            GetRetryOptions::new(2, Timeout::new(1)),
+            || {
+                attempts.set(attempts.get() + 1);
+                future::ready(Err(error.clone()))
+            },
+        ));
+        assert_eq!(result, Err(error));
+        assert_eq!(attempts.get(), 1);
+    }
+}
+
 //#[cfg(test)]
 //pub mod tests {
 //    use crate::instance::tests::test_context_with_state;
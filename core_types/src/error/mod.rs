@@ -111,6 +111,24 @@ impl HolochainError {
     pub fn new(msg: &str) -> HolochainError {
         HolochainError::ErrorGeneric(msg.to_string())
     }
+
+    /// Prefixes the error message with the step that failed, so that errors passed up
+    /// through several steps read like "unblob: pw_dec: <original message>".
+    /// Errors carrying a message keep their kind, all others become an ErrorGeneric.
+    pub fn context(self, step: &str) -> HolochainError {
+        let with_step = |message: String| format!("{}: {}", step, message);
+        match self {
+            ErrorGeneric(message) => ErrorGeneric(with_step(message)),
+            NotImplemented(message) => NotImplemented(with_step(message)),
+            IoError(message) => IoError(with_step(message)),
+            SerializationError(message) => SerializationError(with_step(message)),
+            ValidationFailed(message) => ValidationFailed(with_step(message)),
+            RibosomeFailed(message) => RibosomeFailed(with_step(message)),
+            ConfigError(message) => ConfigError(with_step(message)),
+            InitializationFailed(message) => InitializationFailed(with_step(message)),
+            other => ErrorGeneric(with_step(other.to_string())),
+        }
+    }
 }
 
 impl fmt::Display for HolochainError {
@@ -276,6 +294,26 @@ mod tests {
         );
     }
 
+    #[test]
+    /// test that steps get prepended to the message
+    fn can_add_context() {
+        let err = HolochainError::new("wrong passphrase")
+            .context("pw_dec")
+            .context("from_blob");
+        assert_eq!(
+            HolochainError::ErrorGeneric("from_blob: pw_dec: wrong passphrase".to_string()),
+            err
+        );
+        assert_eq!(
+            HolochainError::IoError("load: no such file".to_string()),
+            HolochainError::IoError("no such file".to_string()).context("load")
+        );
+        assert_eq!(
+            HolochainError::ErrorGeneric("get: timeout".to_string()),
+            HolochainError::Timeout.context("get")
+        );
+    }
+
     #[test]
     /// smoke test new errors
     fn can_instantiate() {
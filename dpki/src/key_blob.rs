@@ -1,5 +1,5 @@
 #![allow(warnings)]
-use holochain_sodium::{kx, secbuf::SecBuf, sign, *};
+use holochain_sodium::{aead, kx, pwhash, secbuf::SecBuf, sign, *};
 
 use crate::{
     key_bundle::*,
@@ -25,6 +25,78 @@ pub struct KeyBlob {
     pub hint: String,
     ///  base64 encoded, json serialized string of the EncryptedData
     pub data: String,
+    /// public key id of the blobbed keys, kept in plain text so that a blob can be
+    /// matched against an expected agent before spending time on decrypting it
+    #[serde(default)]
+    pub id: Option<Base32>,
+}
+
+impl KeyBlob {
+    /// the public key id this blob claims to hold, if it was recorded
+    pub fn expected_id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    /// the plaintext hint, readable without the passphrase
+    pub fn hint(&self) -> &str {
+        &self.hint
+    }
+
+    /// Everything that can be told about the blob without decrypting it, e.g. to let
+    /// a user pick which one to unlock.
+    /// None of it is authenticated: anybody with access to the stored blob can change
+    /// these fields without it being noticed before decryption.
+    pub fn summary(&self) -> KeyBlobSummary {
+        KeyBlobSummary {
+            hint: self.hint.clone(),
+            blob_type: self.blob_type.clone(),
+            seed_type: self.seed_type.clone(),
+            id: self.id.clone(),
+        }
+    }
+
+    /// Check that the blob is well formed without decrypting it: the data has to be
+    /// base64 encoded EncryptedData json, with salt, nonce and cipher of the sizes
+    /// expected for the blob type.
+    /// This does not tell whether the passphrase is right or the content was tampered
+    /// with, only decrypting does.
+    pub fn is_structurally_valid(&self) -> HcResult<()> {
+        let blob_size = match self.blob_type {
+            BlobType::Seed => Seed::blob_size(),
+            BlobType::KeyBundle => KeyBundle::blob_size(),
+            BlobType::SigningKey => SigningKeyPair::blob_size(),
+            BlobType::EncryptingKey => EncryptingKeyPair::blob_size(),
+            // not encrypted, the data is the json of the public keys
+            BlobType::PublicKeyBundle => return PublicKeyBundle::from_blob(self).map(|_| ()),
+        };
+        let blob_b64 =
+            base64::decode(&self.data).map_err(|e| HolochainError::from(e).context("base64"))?;
+        let blob_json =
+            str::from_utf8(&blob_b64).map_err(|e| HolochainError::from(e).context("utf8"))?;
+        let encrypted: EncryptedData = serde_json::from_str(&blob_json)
+            .map_err(|e| HolochainError::from(e).context("json"))?;
+        let check_len = |name: &str, actual: usize, expected: usize| {
+            if actual != expected {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Invalid {} size in {:?} Blob: {} instead of {}",
+                    name, self.blob_type, actual, expected
+                )));
+            }
+            Ok(())
+        };
+        check_len("salt", encrypted.salt.len(), pwhash::SALTBYTES)?;
+        check_len("nonce", encrypted.nonce.len(), aead::NONCEBYTES)?;
+        check_len("cipher", encrypted.cipher.len(), blob_size + aead::ABYTES)
+    }
+}
+
+/// Unauthenticated plaintext information about a KeyBlob, see KeyBlob::summary
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBlobSummary {
+    pub hint: String,
+    pub blob_type: BlobType,
+    pub seed_type: SeedType,
+    pub id: Option<Base32>,
 }
 
 /// Enum of all blobbable types
@@ -34,6 +106,7 @@ pub enum BlobType {
     KeyBundle,
     SigningKey,
     EncryptingKey,
+    PublicKeyBundle,
     // TODO futur blobbables?
     // Key,
 }
@@ -60,6 +133,33 @@ pub trait Blobbable {
 
     // -- Common methods -- //
 
+    /// Like from_blob, but takes ownership of the passphrase and zeroes it before
+    /// returning, so callers don't have to remember to wipe it.
+    fn from_blob_consuming(
+        blob: &KeyBlob,
+        mut passphrase: SecBuf,
+        config: Option<PwHashConfig>,
+    ) -> HcResult<Self>
+    where
+        Self: Sized,
+    {
+        Self::from_blob_zeroing(blob, &mut passphrase, config)
+    }
+
+    /// from_blob followed by zeroing the passphrase, whether unblobbing succeeded or not
+    fn from_blob_zeroing(
+        blob: &KeyBlob,
+        passphrase: &mut SecBuf,
+        config: Option<PwHashConfig>,
+    ) -> HcResult<Self>
+    where
+        Self: Sized,
+    {
+        let result = Self::from_blob(blob, passphrase, config);
+        passphrase.zero();
+        result
+    }
+
     /// Blobs a data buf
     fn finalize_blobbing(
         data_buf: &mut SecBuf,
@@ -89,6 +189,7 @@ pub trait Blobbable {
             ));
         }
         utils::decrypt_with_passphrase_buf(&blob.data, passphrase, config, Self::blob_size())
+            .map_err(|e| e.context(&format!("unblob {:?}", blob.blob_type)))
     }
 }
 
@@ -140,6 +241,7 @@ impl Blobbable for Seed {
             blob_type: BlobType::Seed,
             hint,
             data: encoded_blob,
+            id: None,
         })
     }
 }
@@ -150,14 +252,87 @@ impl Blobbable for Seed {
 
 const KEYBUNDLE_BLOB_FORMAT_VERSION: u8 = 2;
 
-const KEYBUNDLE_BLOB_SIZE: usize = 1 // version byte
-    + sign::PUBLICKEYBYTES
-    + kx::PUBLICKEYBYTES
-    + sign::SECRETKEYBYTES
-    + kx::SECRETKEYBYTES;
+const KEYBUNDLE_BLOB_SIZE: usize = KeyMaterialLayout::END;
 
 pub const KEYBUNDLE_BLOB_SIZE_ALIGNED: usize = ((KEYBUNDLE_BLOB_SIZE + 8 - 1) / 8) * 8;
 
+/// Byte layout of the data buf of a KeyBundle blob:
+/// the version byte, followed by the raw public signing, public encrypting,
+/// private signing and private encrypting keys, padded to KEYBUNDLE_BLOB_SIZE_ALIGNED.
+/// Blobbing and unblobbing both go through it, so they can't drift apart.
+/// Any length or count added to the layout has to be written big endian.
+struct KeyMaterialLayout;
+
+impl KeyMaterialLayout {
+    const VERSION: usize = 0;
+    const PUB_SIGN: usize = KeyMaterialLayout::VERSION + 1;
+    const PUB_ENC: usize = KeyMaterialLayout::PUB_SIGN + sign::PUBLICKEYBYTES;
+    const PRIV_SIGN: usize = KeyMaterialLayout::PUB_ENC + kx::PUBLICKEYBYTES;
+    const PRIV_ENC: usize = KeyMaterialLayout::PRIV_SIGN + sign::SECRETKEYBYTES;
+    const END: usize = KeyMaterialLayout::PRIV_ENC + kx::SECRETKEYBYTES;
+
+    /// Write the keys of a bundle into a new data buf
+    /// @param {KeyBundle} bundle - the keys to write
+    /// @return {SecBuf} the data buf, KEYBUNDLE_BLOB_SIZE_ALIGNED long
+    fn encode(bundle: &mut KeyBundle) -> HcResult<SecBuf> {
+        let mut data_buf = SecBuf::with_secure(KEYBUNDLE_BLOB_SIZE_ALIGNED);
+        data_buf.write(Self::VERSION, &[KEYBUNDLE_BLOB_FORMAT_VERSION])?;
+        // Write public signing key
+        let key = bundle.sign_keys.decode_pub_key();
+        assert_eq!(sign::PUBLICKEYBYTES, key.len());
+        data_buf.write(Self::PUB_SIGN, &key)?;
+        // Write public encoding key
+        let key = bundle.enc_keys.decode_pub_key();
+        assert_eq!(kx::PUBLICKEYBYTES, key.len());
+        data_buf.write(Self::PUB_ENC, &key)?;
+        // Write private keys
+        data_buf.write(Self::PRIV_SIGN, &**bundle.sign_keys.private.read_lock())?;
+        data_buf.write(Self::PRIV_ENC, &**bundle.enc_keys.private.read_lock())?;
+        Ok(data_buf)
+    }
+
+    /// Read the keys back from a data buf written by encode
+    /// @param {SecBuf} data_buf - the data buf
+    /// @return {KeyBundle} the bundle, without retained seed or master commitment
+    fn decode(data_buf: &mut SecBuf) -> HcResult<KeyBundle> {
+        let mut pub_sign = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
+        let mut pub_enc = SecBuf::with_insecure(kx::PUBLICKEYBYTES);
+        let mut priv_sign = SecBuf::with_secure(sign::SECRETKEYBYTES);
+        let mut priv_enc = SecBuf::with_secure(kx::SECRETKEYBYTES);
+        {
+            let data_buf = data_buf.read_lock();
+            if data_buf.len() < Self::END {
+                return Err(HolochainError::ErrorGeneric(
+                    "Invalid KeyBundle Blob size".to_string(),
+                ));
+            }
+            if data_buf[Self::VERSION] != KEYBUNDLE_BLOB_FORMAT_VERSION {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Invalid KeyBundle Blob Format: v{:?} != v{:?}",
+                    data_buf[Self::VERSION],
+                    KEYBUNDLE_BLOB_FORMAT_VERSION
+                )));
+            }
+            pub_sign.write(0, &data_buf[Self::PUB_SIGN..Self::PUB_ENC])?;
+            pub_enc.write(0, &data_buf[Self::PUB_ENC..Self::PRIV_SIGN])?;
+            priv_sign.write(0, &data_buf[Self::PRIV_SIGN..Self::PRIV_ENC])?;
+            priv_enc.write(0, &data_buf[Self::PRIV_ENC..Self::END])?;
+        }
+        Ok(KeyBundle {
+            sign_keys: SigningKeyPair::new(
+                SigningKeyPair::encode_pub_key(&mut pub_sign),
+                priv_sign,
+            ),
+            enc_keys: EncryptingKeyPair::new(
+                EncryptingKeyPair::encode_pub_key(&mut pub_enc),
+                priv_enc,
+            ),
+            retained_seed: None,
+            master_commitment: None,
+        })
+    }
+}
+
 impl Blobbable for KeyBundle {
     fn blob_type() -> BlobType {
         BlobType::KeyBundle
@@ -169,7 +344,8 @@ impl Blobbable for KeyBundle {
 
     /// Generate an encrypted blob for persistence
     /// @param {SecBuf} passphrase - the encryption passphrase
-    /// @param {string} hint - additional info / description for the bundle
+    /// @param {string} hint - additional info / description for the bundle,
+    ///   defaults to "hcKeyBundle:<fingerprint>" if empty
     /// @param {Option<PwHashConfig>} config - Settings for pwhash
     fn as_blob(
         &mut self,
@@ -177,37 +353,12 @@ impl Blobbable for KeyBundle {
         hint: String,
         config: Option<PwHashConfig>,
     ) -> HcResult<KeyBlob> {
-        // Initialize buffer
-        let mut data_buf = SecBuf::with_secure(KEYBUNDLE_BLOB_SIZE_ALIGNED);
-        let mut offset: usize = 0;
-        // Write version
-        data_buf.write(0, &[KEYBUNDLE_BLOB_FORMAT_VERSION]).unwrap();
-        offset += 1;
-        // Write public signing key
-        let key = self.sign_keys.decode_pub_key();
-        assert_eq!(sign::PUBLICKEYBYTES, key.len());
-        data_buf
-            .write(offset, &key)
-            .expect("Failed blobbing public signing key");
-        offset += sign::PUBLICKEYBYTES;
-        // Write public encoding key
-        let key = self.enc_keys.decode_pub_key();
-        assert_eq!(kx::PUBLICKEYBYTES, key.len());
-        data_buf
-            .write(offset, &key)
-            .expect("Failed blobbing public encoding key");
-        offset += kx::PUBLICKEYBYTES;
-        // Write private signing key
-        data_buf
-            .write(offset, &**self.sign_keys.private.read_lock())
-            .expect("Failed blobbing private signing key");
-        offset += sign::SECRETKEYBYTES;
-        // Write private encoding key
-        data_buf
-            .write(offset, &**self.enc_keys.private.read_lock())
-            .expect("Failed blobbing private encoding key");
-        offset += kx::SECRETKEYBYTES;
-        assert_eq!(offset, KEYBUNDLE_BLOB_SIZE);
+        let hint = if hint.is_empty() {
+            format!("hcKeyBundle:{}", self.fingerprint()?)
+        } else {
+            hint
+        };
+        let mut data_buf = KeyMaterialLayout::encode(self)?;
 
         // Finalize
         let encoded_blob = Self::finalize_blobbing(&mut data_buf, passphrase, config)?;
@@ -218,6 +369,7 @@ impl Blobbable for KeyBundle {
             blob_type: BlobType::KeyBundle,
             hint,
             data: encoded_blob,
+            id: Some(self.get_id()),
         })
     }
 
@@ -232,37 +384,79 @@ impl Blobbable for KeyBundle {
     ) -> HcResult<KeyBundle> {
         // Retrieve data buf from blob
         let mut keybundle_blob = Self::unblob(blob, passphrase, config)?;
+        KeyMaterialLayout::decode(&mut keybundle_blob)
+    }
+}
 
-        // Deserialize manually
-        let mut pub_sign = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
-        let mut pub_enc = SecBuf::with_insecure(kx::PUBLICKEYBYTES);
-        let mut priv_sign = SecBuf::with_secure(sign::SECRETKEYBYTES);
-        let mut priv_enc = SecBuf::with_secure(kx::SECRETKEYBYTES);
-        {
-            let keybundle_blob = keybundle_blob.read_lock();
-            if keybundle_blob[0] != KEYBUNDLE_BLOB_FORMAT_VERSION {
-                return Err(HolochainError::ErrorGeneric(format!(
-                    "Invalid KeyBundle Blob Format: v{:?} != v{:?}",
-                    keybundle_blob[0], KEYBUNDLE_BLOB_FORMAT_VERSION
-                )));
+impl KeyBundle {
+    /// Construct the pairs from an encrypted blob, making sure they belong to the expected id
+    /// Fails before decrypting if the blob records a different id.
+    /// @param {object} blob - persistence info
+    /// @param {Base32} expected_id - public key id the blob should hold
+    /// @param {SecBuf} passphrase - decryption passphrase
+    /// @param {Option<PwHashConfig>} config - Settings for pwhash
+    pub fn from_blob_for_id(
+        blob: &KeyBlob,
+        expected_id: &Base32,
+        passphrase: &mut SecBuf,
+        config: Option<PwHashConfig>,
+    ) -> HcResult<KeyBundle> {
+        let mismatch = || {
+            HolochainError::ErrorGeneric(format!(
+                "KeyBundle Blob does not hold the expected id {}",
+                expected_id
+            ))
+        };
+        if let Some(id) = blob.expected_id() {
+            if id != *expected_id {
+                return Err(mismatch());
             }
-            pub_sign.write(0, &keybundle_blob[1..33])?;
-            pub_enc.write(0, &keybundle_blob[33..65])?;
-            priv_sign.write(0, &keybundle_blob[65..129])?;
-            priv_enc.write(0, &keybundle_blob[129..161])?;
         }
-        // Done
-        Ok(KeyBundle {
-            sign_keys: SigningKeyPair::new(
-                SigningKeyPair::encode_pub_key(&mut pub_sign),
-                priv_sign,
-            ),
-            enc_keys: EncryptingKeyPair::new(
-                EncryptingKeyPair::encode_pub_key(&mut pub_enc),
-                priv_enc,
-            ),
+        // blobs without a recorded id are checked after decrypting
+        let bundle = KeyBundle::from_blob(blob, passphrase, config)?;
+        if bundle.get_id() != *expected_id {
+            return Err(mismatch());
+        }
+        Ok(bundle)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// PublicKeyBundle
+//--------------------------------------------------------------------------------------------------
+
+impl PublicKeyBundle {
+    /// Generate a blob for publishing the public keys.
+    /// The data is plain json: there is nothing secret to encrypt.
+    /// @param {string} hint - additional info / description for the bundle
+    pub fn as_blob(&self, hint: String) -> HcResult<KeyBlob> {
+        Ok(KeyBlob {
+            seed_type: SeedType::Mock,
+            blob_type: BlobType::PublicKeyBundle,
+            hint,
+            data: serde_json::to_string(self)?,
+            id: Some(self.sign_id.clone()),
         })
     }
+
+    /// Read the public keys back from a blob made by as_blob
+    /// @param {object} blob - the public bundle blob
+    pub fn from_blob(blob: &KeyBlob) -> HcResult<Self> {
+        if blob.blob_type != BlobType::PublicKeyBundle {
+            return Err(HolochainError::ErrorGeneric(
+                "Blob type mismatch while unblobbing".to_string(),
+            ));
+        }
+        let bundle: PublicKeyBundle = serde_json::from_str(&blob.data)?;
+        if let Some(id) = blob.expected_id() {
+            if id != bundle.sign_id {
+                return Err(HolochainError::ErrorGeneric(
+                    "PublicKeyBundle Blob id does not match its keys".to_string(),
+                ));
+            }
+        }
+        Ok(bundle)
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -326,6 +520,7 @@ impl Blobbable for SigningKeyPair {
             blob_type: BlobType::SigningKey,
             hint,
             data: encoded_blob,
+            id: Some(self.public()),
         })
     }
 
@@ -424,6 +619,7 @@ impl Blobbable for EncryptingKeyPair {
             blob_type: BlobType::EncryptingKey,
             hint,
             data: encoded_blob,
+            id: None,
         })
     }
 
@@ -498,6 +694,215 @@ mod tests {
         assert!(maybe_unblob.is_err());
     }
 
+    #[test]
+    fn it_should_check_blob_structure_without_passphrase() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let mut blob = bundle
+            .as_blob(&mut passphrase, "hint".to_string(), TEST_CONFIG)
+            .unwrap();
+        assert!(blob.is_structurally_valid().is_ok());
+
+        // claiming another type makes the cipher size wrong:
+        blob.blob_type = BlobType::Seed;
+        assert!(blob.is_structurally_valid().is_err());
+        blob.blob_type = BlobType::KeyBundle;
+
+        let data = blob.data.clone();
+        blob.data = "not base64!".to_string();
+        assert!(blob.is_structurally_valid().is_err());
+
+        blob.data = base64::encode("{\"salt\":[1,2,3]}");
+        assert!(blob.is_structurally_valid().is_err());
+
+        blob.data = data;
+        assert!(blob.is_structurally_valid().is_ok());
+    }
+
+    #[test]
+    fn it_should_default_the_hint_to_the_fingerprint() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let fingerprint = bundle.fingerprint().unwrap();
+        assert_eq!(8, fingerprint.len());
+
+        let blob = bundle
+            .as_blob(&mut passphrase, "".to_string(), TEST_CONFIG)
+            .unwrap();
+        assert_eq!(format!("hcKeyBundle:{}", fingerprint), blob.hint);
+
+        let blob = bundle
+            .as_blob(&mut passphrase, "mine".to_string(), TEST_CONFIG)
+            .unwrap();
+        assert_eq!("mine", blob.hint);
+    }
+
+    #[test]
+    fn it_should_lay_out_key_material_at_fixed_positions() {
+        assert_eq!(1, KeyMaterialLayout::PUB_SIGN);
+        assert_eq!(33, KeyMaterialLayout::PUB_ENC);
+        assert_eq!(65, KeyMaterialLayout::PRIV_SIGN);
+        assert_eq!(129, KeyMaterialLayout::PRIV_ENC);
+        assert_eq!(161, KeyMaterialLayout::END);
+        assert_eq!(168, KEYBUNDLE_BLOB_SIZE_ALIGNED);
+
+        let mut seed_buf = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let mut data_buf = KeyMaterialLayout::encode(&mut bundle).unwrap();
+        {
+            let data = data_buf.read_lock();
+            assert_eq!(KEYBUNDLE_BLOB_FORMAT_VERSION, data[0]);
+            assert_eq!(bundle.sign_keys.decode_pub_key(), data[1..33].to_vec());
+            assert_eq!(bundle.enc_keys.decode_pub_key(), data[33..65].to_vec());
+            assert_eq!(bundle.sign_keys.private.read_lock()[..], data[65..129]);
+            assert_eq!(bundle.enc_keys.private.read_lock()[..], data[129..161]);
+        }
+
+        let mut decoded = KeyMaterialLayout::decode(&mut data_buf).unwrap();
+        assert!(bundle.sign_keys.is_same(&mut decoded.sign_keys));
+        assert!(bundle.enc_keys.is_same(&mut decoded.enc_keys));
+
+        data_buf
+            .write(0, &[KEYBUNDLE_BLOB_FORMAT_VERSION + 1])
+            .unwrap();
+        assert!(KeyMaterialLayout::decode(&mut data_buf).is_err());
+    }
+
+    #[test]
+    fn it_should_report_the_failing_unblob_step() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let mut blob = bundle
+            .as_blob(&mut passphrase, "hint".to_string(), TEST_CONFIG)
+            .unwrap();
+
+        blob.data = "not base64!".to_string();
+        let error = KeyBundle::from_blob(&blob, &mut passphrase, TEST_CONFIG)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("unblob KeyBundle: base64: "));
+
+        blob.data = base64::encode("not json");
+        let error = KeyBundle::from_blob(&blob, &mut passphrase, TEST_CONFIG)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("unblob KeyBundle: json: "));
+    }
+
+    #[test]
+    fn it_should_unblob_consuming_the_passphrase() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = SecBuf::with_insecure_from_string("a passphrase".to_string());
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let blob = bundle
+            .as_blob(&mut passphrase, "hint".to_string(), TEST_CONFIG)
+            .unwrap();
+
+        let mut unblob =
+            KeyBundle::from_blob_consuming(&blob, passphrase.clone(), TEST_CONFIG).unwrap();
+        assert!(bundle.is_same(&mut unblob));
+
+        let mut unblob = KeyBundle::from_blob_zeroing(&blob, &mut passphrase, TEST_CONFIG).unwrap();
+        assert!(bundle.is_same(&mut unblob));
+        assert!(passphrase.read_lock().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn it_should_check_expected_id_of_keybundle_blob() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let other_id = KeyBundle::new_from_seed_buf(&mut generate_random_seed_buf())
+            .unwrap()
+            .get_id();
+
+        let mut blob = bundle
+            .as_blob(&mut passphrase, "hint".to_string(), TEST_CONFIG)
+            .unwrap();
+        assert_eq!(Some(bundle.get_id()), blob.expected_id());
+
+        let mut unblob =
+            KeyBundle::from_blob_for_id(&blob, &bundle.get_id(), &mut passphrase, TEST_CONFIG)
+                .unwrap();
+        assert!(bundle.is_same(&mut unblob));
+
+        // A wrong passphrase would fail decoding, so getting the id mismatch error
+        // shows the blob got rejected before decrypting it.
+        let mut wrong_passphrase = generate_random_seed_buf();
+        let expected_error = HolochainError::ErrorGeneric(format!(
+            "KeyBundle Blob does not hold the expected id {}",
+            other_id
+        ));
+        assert_eq!(
+            Err(expected_error.clone()),
+            KeyBundle::from_blob_for_id(&blob, &other_id, &mut wrong_passphrase, TEST_CONFIG)
+                .map(|_| ())
+        );
+
+        // blobs without a recorded id get checked after decrypting
+        blob.id = None;
+        assert_eq!(
+            Err(expected_error),
+            KeyBundle::from_blob_for_id(&blob, &other_id, &mut passphrase, TEST_CONFIG).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn it_should_blob_public_keybundle() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let public_bundle = bundle.export_public_bundle();
+        assert_eq!(bundle.get_id(), public_bundle.sign_id);
+
+        let blob = public_bundle.as_blob("published".to_string()).unwrap();
+        assert_eq!(BlobType::PublicKeyBundle, blob.blob_type);
+        let unblob = PublicKeyBundle::from_blob(&blob).unwrap();
+        assert_eq!(public_bundle, unblob);
+
+        let mut message = SecBuf::with_insecure(16);
+        message.randomize();
+        let mut signature = bundle.sign(&mut message).unwrap();
+        assert!(unblob.verify(&mut message, &mut signature).unwrap());
+        message.randomize();
+        assert!(!unblob.verify(&mut message, &mut signature).unwrap());
+
+        // a public bundle is no KeyBundle, there is nothing to sign with:
+        let mut passphrase = generate_random_seed_buf();
+        assert!(KeyBundle::from_blob(&blob, &mut passphrase, TEST_CONFIG).is_err());
+    }
+
+    #[test]
+    fn it_should_summarize_blob_without_passphrase() {
+        let mut seed_buf = generate_random_seed_buf();
+        let mut passphrase = generate_random_seed_buf();
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        let blob = bundle
+            .as_blob(&mut passphrase, "my laptop".to_string(), TEST_CONFIG)
+            .unwrap();
+
+        assert_eq!("my laptop", blob.hint());
+        assert_eq!(
+            KeyBlobSummary {
+                hint: "my laptop".to_string(),
+                blob_type: BlobType::KeyBundle,
+                seed_type: SeedType::Mock,
+                id: Some(bundle.get_id()),
+            },
+            blob.summary()
+        );
+    }
+
+    #[test]
+    fn it_should_load_key_blob_without_id() {
+        let json =
+            "{\"blob_type\":\"KeyBundle\",\"seed_type\":\"Mock\",\"hint\":\"hint\",\"data\":\"\"}";
+        let blob: KeyBlob = serde_json::from_str(json).unwrap();
+        assert_eq!(None, blob.expected_id());
+    }
+
     #[test]
     fn it_should_blob_signing_key() {
         let mut passphrase = generate_random_seed_buf();
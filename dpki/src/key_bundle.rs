@@ -5,9 +5,14 @@ use crate::{
     keypair::*,
     password_encryption::{self, EncryptedData, PwHashConfig},
     seed::{Seed, SeedType},
-    utils, SEED_SIZE,
+    utils, CODEC_HCK0, CODEC_HCS0, SEED_SIZE,
 };
-use holochain_core_types::{agent::Base32, error::HcResult};
+use hcid::HcidEncoding;
+use holochain_core_types::{
+    agent::Base32,
+    error::{HcResult, HolochainError},
+};
+use log::debug;
 use serde_json::json;
 use std::str;
 
@@ -17,6 +22,25 @@ use serde_derive::{Deserialize, Serialize};
 pub struct KeyBundle {
     pub sign_keys: SigningKeyPair,
     pub enc_keys: EncryptingKeyPair,
+    /// the seed the keys were derived from, only kept when explicitly requested
+    pub(crate) retained_seed: Option<SecBuf>,
+    /// salted hash of the master seed, only set on bundles derived through a RootSeed
+    pub(crate) master_commitment: Option<Vec<u8>>,
+}
+
+/// Tell whether two KeyBundles were derived from the same master seed,
+/// without needing (or exposing) that seed.
+/// Fails if either bundle was not derived through a RootSeed.
+/// @param {KeyBundle} a - first bundle
+/// @param {KeyBundle} b - second bundle
+/// @return true if both descend from the same master seed
+pub fn same_master(a: &KeyBundle, b: &KeyBundle) -> HcResult<bool> {
+    match (&a.master_commitment, &b.master_commitment) {
+        (Some(a), Some(b)) => Ok(a == b),
+        _ => Err(HolochainError::ErrorGeneric(
+            "KeyBundle carries no master seed commitment".to_string(),
+        )),
+    }
 }
 
 impl KeyBundle {
@@ -25,6 +49,8 @@ impl KeyBundle {
         Ok(KeyBundle {
             sign_keys,
             enc_keys,
+            retained_seed: None,
+            master_commitment: None,
         })
     }
 
@@ -33,6 +59,8 @@ impl KeyBundle {
         Ok(KeyBundle {
             sign_keys: SigningKeyPair::new_from_seed(&mut seed.buf)?,
             enc_keys: EncryptingKeyPair::new_from_seed(&mut seed.buf)?,
+            retained_seed: None,
+            master_commitment: None,
         })
     }
 
@@ -41,10 +69,57 @@ impl KeyBundle {
     /// @param {SeedType} seed_type - seed type of the buffer
     pub fn new_from_seed_buf(seed_buf: &mut SecBuf) -> HcResult<Self> {
         assert_eq!(seed_buf.len(), SEED_SIZE);
-        Ok(KeyBundle {
+        let bundle = KeyBundle {
             sign_keys: SigningKeyPair::new_from_seed(seed_buf)?,
             enc_keys: EncryptingKeyPair::new_from_seed(seed_buf)?,
-        })
+            retained_seed: None,
+            master_commitment: None,
+        };
+        debug!("derived KeyBundle {}", bundle.get_id());
+        Ok(bundle)
+    }
+
+    /// Derive one KeyBundle per 32 bytes seed buffer, e.g. to provision many agents at once
+    /// @param {[SecBuf]} seed_bufs - the seed buffers
+    /// @return the bundles in the order of the seeds, or an error naming every failed index
+    pub fn batch_from_seeds(seed_bufs: &mut [SecBuf]) -> HcResult<Vec<Self>> {
+        let mut bundles = Vec::with_capacity(seed_bufs.len());
+        let mut failures = Vec::new();
+        for (index, seed_buf) in seed_bufs.iter_mut().enumerate() {
+            if seed_buf.len() != SEED_SIZE {
+                failures.push(format!("{}: seed must be {} bytes", index, SEED_SIZE));
+                continue;
+            }
+            match KeyBundle::new_from_seed_buf(seed_buf) {
+                Ok(bundle) => bundles.push(bundle),
+                Err(error) => failures.push(format!("{}: {}", index, error)),
+            }
+        }
+        if !failures.is_empty() {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Could not derive KeyBundles for seeds {}",
+                failures.join(", ")
+            )));
+        }
+        Ok(bundles)
+    }
+
+    /// Derive the keys from a 32 bytes seed buffer and keep a copy of the seed
+    /// in secure memory, so that it can later be handed out with `export_seed()`.
+    /// Only use this for migration tools, the default constructors drop the seed.
+    /// @param {SecBuf} seed - the seed buffer
+    pub fn new_from_seed_buf_retaining(seed_buf: &mut SecBuf) -> HcResult<Self> {
+        let mut bundle = KeyBundle::new_from_seed_buf(seed_buf)?;
+        let mut retained_seed = SecBuf::with_secure(SEED_SIZE);
+        retained_seed.write(0, &seed_buf.read_lock())?;
+        bundle.retained_seed = Some(retained_seed);
+        Ok(bundle)
+    }
+
+    /// Hand out the seed this bundle was derived from, if it was retained.
+    /// The bundle gives up its copy, so this returns Some at most once.
+    pub fn export_seed(&mut self) -> Option<SecBuf> {
+        self.retained_seed.take()
     }
 
     /// get the identifier key
@@ -52,6 +127,24 @@ impl KeyBundle {
         self.sign_keys.public.clone()
     }
 
+    /// short identifier of the bundle that doesn't give away the full id:
+    /// the first 8 hex digits of the SHA-256 hash of the public signing key
+    pub fn fingerprint(&self) -> HcResult<String> {
+        let mut pub_key = utils::decode_pub_key(self.get_id(), &CODEC_HCS0)?;
+        let mut digest = SecBuf::with_insecure(hash::BYTES256);
+        hash::sha256(&mut pub_key, &mut digest)?;
+        let digest = digest.read_lock();
+        Ok(digest[..4].iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// get only the public keys, e.g. to publish them
+    pub fn export_public_bundle(&self) -> PublicKeyBundle {
+        PublicKeyBundle {
+            sign_id: self.sign_keys.public.clone(),
+            enc_id: self.enc_keys.public.clone(),
+        }
+    }
+
     /// sign some arbitrary data with the signing private key
     /// @param {SecBuf} data - the data to sign
     /// @return {SecBuf} signature - Empty Buf to be filled with the signature
@@ -62,8 +155,8 @@ impl KeyBundle {
     /// verify data that was signed with our private signing key
     /// @param {SecBuf} data buffer to verify
     /// @param {SecBuf} signature candidate for that data buffer
-    /// @return true if verification succeeded
-    pub fn verify(&mut self, data: &mut SecBuf, signature: &mut SecBuf) -> bool {
+    /// @return true if verification succeeded, an error if the signature is malformed
+    pub fn verify(&mut self, data: &mut SecBuf, signature: &mut SecBuf) -> HcResult<bool> {
         self.sign_keys.verify(data, signature)
     }
 
@@ -73,6 +166,90 @@ impl KeyBundle {
     }
 }
 
+/// The public keys of a KeyBundle.
+/// Can be used to verify the agent's signatures, but holds nothing to sign
+/// or decrypt with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeyBundle {
+    pub sign_id: Base32,
+    pub enc_id: Base32,
+}
+
+impl PublicKeyBundle {
+    /// Build the ids from raw public keys, e.g. ones that were stored outside of a KeyBlob
+    /// @param {[u8; 32]} sign_pub - public signing key
+    /// @param {[u8; 32]} enc_pub - public encryption key
+    pub fn from_bytes(sign_pub: &[u8; 32], enc_pub: &[u8; 32]) -> HcResult<Self> {
+        Ok(PublicKeyBundle {
+            sign_id: CODEC_HCS0.encode(sign_pub)?,
+            enc_id: CODEC_HCK0.encode(enc_pub)?,
+        })
+    }
+
+    /// The raw public keys behind the ids, the reverse of from_bytes
+    /// @return ([u8; 32], [u8; 32]) - public signing key and public encryption key
+    pub fn to_bytes(&self) -> HcResult<([u8; 32], [u8; 32])> {
+        Ok((
+            decode_key_bytes(&self.sign_id, &CODEC_HCS0)?,
+            decode_key_bytes(&self.enc_id, &CODEC_HCK0)?,
+        ))
+    }
+
+    /// verify data that was signed with the matching private signing key
+    /// @param {SecBuf} data buffer to verify
+    /// @param {SecBuf} signature candidate for that data buffer
+    /// @return true if verification succeeded
+    pub fn verify(&self, data: &mut SecBuf, signature: &mut SecBuf) -> HcResult<bool> {
+        utils::verify_bufs(self.sign_id.clone(), data, signature)
+    }
+}
+
+fn decode_key_bytes(id: &Base32, codec: &HcidEncoding) -> HcResult<[u8; 32]> {
+    let decoded = codec.decode(id)?;
+    if decoded.len() != 32 {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Invalid public key length: {}",
+            decoded.len()
+        )));
+    }
+    let mut key = [0; 32];
+    key.copy_from_slice(&decoded);
+    Ok(key)
+}
+
+#[cfg(any(test, feature = "test-fixtures"))]
+impl KeyBundle {
+    /// Deterministic KeyBundle for test fixtures: derived from a fixed seed and
+    /// the given index, so the same index yields the same id across runs.
+    /// Never use these keys outside of tests.
+    pub fn new_test(index: u8) -> Self {
+        let mut fixture_seed = SecBuf::with_insecure(SEED_SIZE);
+        fixture_seed
+            .write(0, &[0x42; SEED_SIZE])
+            .expect("SecBuf must be writeable");
+        let context = utils::SeedContext::new(*b"HCTESTKB");
+        // kdf indices start at 1
+        let mut seed_buf = utils::generate_derived_seed_buf(
+            &mut fixture_seed,
+            &context,
+            index as u64 + 1,
+            SEED_SIZE,
+        )
+        .expect("could not derive test fixture seed");
+        KeyBundle::new_from_seed_buf(&mut seed_buf).expect("could not create test KeyBundle")
+    }
+
+    /// Deterministic KeyBundle for test fixtures, seeded with the SHA-256 hash of a
+    /// readable label, so that e.g. `from_label("alice")` is always the same agent.
+    /// Never use these keys outside of tests.
+    pub fn from_label(label: &str) -> Self {
+        let mut label_buf = SecBuf::with_insecure_from_string(label.to_string());
+        let mut seed_buf = SecBuf::with_insecure(SEED_SIZE);
+        hash::sha256(&mut label_buf, &mut seed_buf).expect("could not hash test label");
+        KeyBundle::new_from_seed_buf(&mut seed_buf).expect("could not create test KeyBundle")
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
@@ -113,6 +290,83 @@ pub(crate) mod tests {
         assert_ne!(0, id.len());
     }
 
+    #[test]
+    fn it_should_create_deterministic_test_bundles() {
+        let mut bundle_1 = KeyBundle::new_test(1);
+        assert_eq!(bundle_1.get_id(), KeyBundle::new_test(1).get_id());
+        assert!(bundle_1.is_same(&mut KeyBundle::new_test(1)));
+        assert_ne!(bundle_1.get_id(), KeyBundle::new_test(2).get_id());
+        assert_ne!(KeyBundle::new_test(0).get_id(), bundle_1.get_id());
+    }
+
+    #[test]
+    fn it_should_create_test_bundles_from_labels() {
+        let mut alice = KeyBundle::from_label("alice");
+        let bob = KeyBundle::from_label("bob");
+        assert!(alice.is_same(&mut KeyBundle::from_label("alice")));
+        assert_eq!(bob.get_id(), KeyBundle::from_label("bob").get_id());
+        assert_ne!(alice.get_id(), bob.get_id());
+    }
+
+    #[test]
+    fn it_should_create_keybundles_in_batch() {
+        let mut seeds: Vec<SecBuf> = (0..3).map(|_| generate_random_seed_buf()).collect();
+        let bundles = KeyBundle::batch_from_seeds(&mut seeds).unwrap();
+        assert_eq!(3, bundles.len());
+        assert_ne!(bundles[0].get_id(), bundles[1].get_id());
+        assert_ne!(bundles[1].get_id(), bundles[2].get_id());
+        assert_ne!(bundles[0].get_id(), bundles[2].get_id());
+        assert_eq!(
+            bundles[1].get_id(),
+            KeyBundle::new_from_seed_buf(&mut seeds[1])
+                .unwrap()
+                .get_id()
+        );
+
+        seeds[2] = SecBuf::with_insecure(16);
+        assert_eq!(
+            Err(HolochainError::ErrorGeneric(
+                "Could not derive KeyBundles for seeds 2: seed must be 32 bytes".to_string()
+            )),
+            KeyBundle::batch_from_seeds(&mut seeds).map(|bundles| bundles.len())
+        );
+    }
+
+    #[test]
+    fn it_should_export_retained_seed_only_when_asked() {
+        let mut seed = generate_random_seed_buf();
+        assert!(KeyBundle::new_from_seed_buf(&mut seed)
+            .unwrap()
+            .export_seed()
+            .is_none());
+
+        let mut bundle = KeyBundle::new_from_seed_buf_retaining(&mut seed).unwrap();
+        let mut exported = bundle.export_seed().unwrap();
+        assert!(bundle.export_seed().is_none());
+        assert_eq!(0, exported.compare(&mut seed));
+
+        let mut rederived = KeyBundle::new_from_seed_buf(&mut exported).unwrap();
+        assert!(bundle.is_same(&mut rederived));
+    }
+
+    #[test]
+    fn it_should_build_public_bundle_from_bytes() {
+        let sign_pub = [7; 32];
+        let enc_pub = [42; 32];
+        let public_bundle = PublicKeyBundle::from_bytes(&sign_pub, &enc_pub).unwrap();
+        assert!(public_bundle.sign_id.starts_with("HcS"));
+        assert!(public_bundle.enc_id.starts_with("HcK"));
+        assert_eq!((sign_pub, enc_pub), public_bundle.to_bytes().unwrap());
+
+        let bundle = test_generate_random_bundle();
+        let exported = bundle.export_public_bundle();
+        let (sign_pub, enc_pub) = exported.to_bytes().unwrap();
+        assert_eq!(
+            exported,
+            PublicKeyBundle::from_bytes(&sign_pub, &enc_pub).unwrap()
+        );
+    }
+
     #[test]
     fn keybundle_should_sign_message_and_verify() {
         let mut bundle = test_generate_random_bundle();
@@ -124,19 +378,19 @@ pub(crate) mod tests {
         // sign it
         let mut signature = bundle.sign(&mut message).unwrap();
         // authentify signature
-        let succeeded = bundle.verify(&mut message, &mut signature);
+        let succeeded = bundle.verify(&mut message, &mut signature).unwrap();
         assert!(succeeded);
 
         // Create random data
         let mut random_signature = SecBuf::with_insecure(SIGNATURE_SIZE);
         random_signature.randomize();
         // authentify random signature
-        let succeeded = bundle.verify(&mut message, &mut random_signature);
+        let succeeded = bundle.verify(&mut message, &mut random_signature).unwrap();
         assert!(!succeeded);
 
         // Randomize data again
         message.randomize();
-        let succeeded = bundle.verify(&mut message, &mut signature);
+        let succeeded = bundle.verify(&mut message, &mut signature).unwrap();
         assert!(!succeeded);
     }
 }
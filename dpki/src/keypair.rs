@@ -3,11 +3,16 @@
 use crate::{
     key_bundle,
     password_encryption::{self, PwHashConfig},
-    utils, CODEC_HCK0, CODEC_HCS0, SEED_SIZE, SIGNATURE_SIZE,
+    utils::{self, SeedContext},
+    CODEC_HCK0, CODEC_HCS0, SEED_SIZE, SIGNATURE_SIZE,
 };
 use hcid::*;
-use holochain_core_types::{agent::Base32, error::HcResult};
-use holochain_sodium::{kx, secbuf::SecBuf, sign};
+use holochain_core_types::{
+    agent::Base32,
+    error::{HcResult, HolochainError},
+};
+use holochain_sodium::{hash, kdf, kx, secbuf::SecBuf, sign};
+use log::{debug, trace};
 use serde_json::json;
 use std::str;
 
@@ -46,9 +51,12 @@ pub trait KeyPair {
             .expect("Public key decoding failed. Key was not properly encoded.")
     }
 
-    /// Return true if the keys are equivalent
+    /// Return true if the keys are equivalent, i.e. both the public and the private keys match.
+    /// The private keys are compared in constant time.
     fn is_same(&mut self, other: &mut Self) -> bool {
-        self.public() == other.public() && self.private().compare(other.private()) == 0
+        self.public() == other.public()
+            && self.private().len() == other.private().len()
+            && self.private().compare(other.private()) == 0
     }
 }
 
@@ -107,6 +115,7 @@ impl SigningKeyPair {
     /// @param {SecBuf} data - the data to sign
     /// @return {SecBuf} signature - Empty SecBuf to be filled with the signature
     pub fn sign(&mut self, data: &mut SecBuf) -> HcResult<SecBuf> {
+        trace!("sign {} bytes with {}", data.len(), self.public);
         let mut signature = SecBuf::with_insecure(SIGNATURE_SIZE);
         holochain_sodium::sign::sign(data, &mut self.private, &mut signature)?;
         Ok(signature)
@@ -115,13 +124,259 @@ impl SigningKeyPair {
     /// verify data that was signed with our private signing key
     /// @param {SecBuf} data
     /// @param {SecBuf} signature
-    /// @return true if verification succeeded
-    pub fn verify(&mut self, data: &mut SecBuf, signature: &mut SecBuf) -> bool {
+    /// @return true if verification succeeded, an error if the signature is malformed
+    pub fn verify(&mut self, data: &mut SecBuf, signature: &mut SecBuf) -> HcResult<bool> {
+        utils::check_signature_size(signature)?;
         let mut pub_key = self.decode_pub_key_into_secbuf();
-        holochain_sodium::sign::verify(signature, data, &mut pub_key)
+        let verified = holochain_sodium::sign::verify(signature, data, &mut pub_key);
+        trace!(
+            "verify {} bytes with {}: {}",
+            data.len(),
+            self.public,
+            verified
+        );
+        Ok(verified)
+    }
+
+    /// sign some arbitrary data together with an expiry time
+    /// @param {SecBuf} data - the data to sign
+    /// @param {u64} expires_at - time (seconds since UNIX epoch) from which on the signature is invalid
+    /// @return {ExpiringSignature} signature covering both the data and the expiry
+    pub fn sign_expiring(
+        &mut self,
+        data: &mut SecBuf,
+        expires_at: u64,
+    ) -> HcResult<ExpiringSignature> {
+        let mut message = expiring_message(data, expires_at)?;
+        Ok(ExpiringSignature {
+            signature: self.sign(&mut message)?,
+            expires_at,
+        })
+    }
+
+    /// verify data that was signed with sign_expiring
+    /// @param {ExpiringSignature} signature - the signature and its claimed expiry
+    /// @param {SecBuf} data
+    /// @param {u64} now - current time (seconds since UNIX epoch)
+    /// @return true if verification succeeded and the signature has not expired yet,
+    ///   false for malformed signatures as well
+    pub fn verify_expiring(
+        &mut self,
+        signature: &mut ExpiringSignature,
+        data: &mut SecBuf,
+        now: u64,
+    ) -> bool {
+        if now >= signature.expires_at {
+            return false;
+        }
+        match expiring_message(data, signature.expires_at) {
+            Ok(mut message) => self
+                .verify(&mut message, &mut signature.signature)
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// sign some arbitrary data for a given purpose (e.g. "entry", "message"), so that the
+    /// signature does not verify for any other purpose
+    /// @param {str} context - the purpose of the signature
+    /// @param {SecBuf} data - the data to sign
+    /// @return {SecBuf} signature - only valid together with the same context
+    pub fn sign_with_context(&mut self, context: &str, data: &mut SecBuf) -> HcResult<SecBuf> {
+        let mut message = context_message(context, data)?;
+        self.sign(&mut message)
+    }
+
+    /// verify data that was signed with sign_with_context
+    /// @param {str} context - the purpose the signature must have been made for
+    /// @param {SecBuf} data
+    /// @param {SecBuf} signature
+    /// @return true if verification succeeded, false for malformed signatures as well
+    pub fn verify_with_context(
+        &mut self,
+        context: &str,
+        data: &mut SecBuf,
+        signature: &mut SecBuf,
+    ) -> bool {
+        match context_message(context, data) {
+            Ok(mut message) => self.verify(&mut message, signature).unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// bind a user chosen handle (e.g. a username) to this key, so that a directory can
+    /// map handles to agents without anybody else claiming the same handle for their key
+    /// @param {str} handle - the handle to bind, must not be empty
+    /// @return {HandleCommitment} the handle, the id it is bound to and a signature over both
+    pub fn bind_handle(&mut self, handle: &str) -> HcResult<HandleCommitment> {
+        if handle.is_empty() {
+            return Err(HolochainError::ErrorGeneric(
+                "Can not bind an empty handle".to_string(),
+            ));
+        }
+        let id = self.public();
+        let mut message = handle_message(handle, &id);
+        Ok(HandleCommitment {
+            handle: handle.to_string(),
+            signature: self.sign_with_context(HANDLE_CONTEXT, &mut message)?,
+            id,
+        })
     }
 }
 
+const HANDLE_CONTEXT: &str = "handle";
+
+const PAIRING_CHECKSUM_SIZE: usize = 4;
+const PAIRING_GROUP_SIZE: usize = 4;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+impl SigningKeyPair {
+    /// The public id in a form that is easy to read out, type in or put in a QR code:
+    /// the raw public key followed by a checksum, uppercase base32 in dash separated groups.
+    pub fn pairing_code(&self) -> String {
+        let mut code = self.decode_pub_key();
+        let checksum = pairing_checksum(&code).expect("sha256 of a public key can't fail");
+        code.extend_from_slice(&checksum);
+        let encoded = base32_encode(&code);
+        encoded
+            .as_bytes()
+            .chunks(PAIRING_GROUP_SIZE)
+            .map(|group| str::from_utf8(group).expect("base32 is ascii"))
+            .collect::<Vec<&str>>()
+            .join("-")
+    }
+
+    /// Get the public id back from a pairing code, checking it was transferred correctly.
+    /// Case, dashes and whitespace are ignored.
+    /// @param {str} code - as created by pairing_code
+    /// @return {Base32} the HCID encoded public signing key
+    pub fn id_from_pairing_code(code: &str) -> HcResult<Base32> {
+        let invalid = || HolochainError::ErrorGeneric("Invalid pairing code".to_string());
+        let code: String = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect();
+        let decoded = base32_decode(&code.to_uppercase()).ok_or_else(invalid)?;
+        if decoded.len() != sign::PUBLICKEYBYTES + PAIRING_CHECKSUM_SIZE {
+            return Err(invalid());
+        }
+        let (pub_key, checksum) = decoded.split_at(sign::PUBLICKEYBYTES);
+        if pairing_checksum(pub_key)? != checksum {
+            return Err(HolochainError::ErrorGeneric(
+                "Pairing code checksum mismatch".to_string(),
+            ));
+        }
+        let mut pub_key_sec = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
+        pub_key_sec.write(0, pub_key)?;
+        utils::encode_pub_key(&mut pub_key_sec, &CODEC_HCS0)
+    }
+}
+
+/// the first bytes of the SHA-256 hash of the public key
+fn pairing_checksum(pub_key: &[u8]) -> HcResult<Vec<u8>> {
+    let mut input = SecBuf::with_insecure(pub_key.len());
+    input.write(0, pub_key)?;
+    let mut digest = utils::sha256_of(&mut input)?;
+    digest.truncate(PAIRING_CHECKSUM_SIZE);
+    Ok(digest)
+}
+
+/// RFC 4648 base32 without padding
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut bits: u16 = 0;
+    let mut bit_count = 0;
+    for byte in data {
+        bits = (bits << 8) | u16::from(*byte);
+        bit_count += 8;
+        while bit_count >= 5 {
+            bit_count -= 5;
+            encoded.push(BASE32_ALPHABET[((bits >> bit_count) & 31) as usize] as char);
+        }
+    }
+    if bit_count > 0 {
+        encoded.push(BASE32_ALPHABET[((bits << (5 - bit_count)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// inverse of base32_encode, None if there are characters outside the alphabet
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut bits: u16 = 0;
+    let mut bit_count = 0;
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u16;
+        bits = (bits << 5) | value;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// A handle bound to an agent id by bind_handle
+pub struct HandleCommitment {
+    pub handle: String,
+    /// the public signing key id the handle was bound to
+    pub id: Base32,
+    pub signature: SecBuf,
+}
+
+/// the bytes that get signed for a HandleCommitment: the id followed by the handle
+fn handle_message(handle: &str, id: &Base32) -> SecBuf {
+    SecBuf::with_insecure_from_string(format!("{}{}", id, handle))
+}
+
+/// verify that a handle was bound to the given agent id by its owner
+/// @param {HandleCommitment} commitment - as returned by bind_handle
+/// @param {Base32} id - the id the handle is expected to be bound to
+/// @return true if the commitment is for that id and its signature verifies
+pub fn verify_handle(commitment: &mut HandleCommitment, id: &Base32) -> HcResult<bool> {
+    if commitment.id != *id {
+        return Ok(false);
+    }
+    let mut message = handle_message(&commitment.handle, id);
+    utils::verify_bufs_with_context(
+        id.clone(),
+        HANDLE_CONTEXT,
+        &mut message,
+        &mut commitment.signature,
+    )
+}
+
+/// the bytes that get signed with a context: the SHA-256 hash of the context followed by the
+/// data. The fixed size hash keeps the context from running into the data.
+pub(crate) fn context_message(context: &str, data: &mut SecBuf) -> HcResult<SecBuf> {
+    let mut context_buf = SecBuf::with_insecure_from_string(context.to_string());
+    let mut context_hash = SecBuf::with_insecure(hash::BYTES256);
+    hash::sha256(&mut context_buf, &mut context_hash)?;
+    let data = data.read_lock();
+    let mut message = SecBuf::with_insecure(hash::BYTES256 + data.len());
+    message.write(0, &**context_hash.read_lock())?;
+    message.write(hash::BYTES256, &**data)?;
+    Ok(message)
+}
+
+/// A signature that is only valid until a given time.
+/// The expiry is part of the signed bytes, so changing it invalidates the signature.
+pub struct ExpiringSignature {
+    pub signature: SecBuf,
+    /// seconds since UNIX epoch from which on the signature is invalid
+    pub expires_at: u64,
+}
+
+/// the bytes that get signed for an ExpiringSignature: the big endian expiry followed by the data
+fn expiring_message(data: &mut SecBuf, expires_at: u64) -> HcResult<SecBuf> {
+    let data = data.read_lock();
+    let mut message = SecBuf::with_insecure(8 + data.len());
+    message.write(0, &expires_at.to_be_bytes())?;
+    message.write(8, &**data)?;
+    Ok(message)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Encrypting KeyPair
 //--------------------------------------------------------------------------------------------------
@@ -173,16 +428,82 @@ impl EncryptingKeyPair {
         Self { public, private }
     }
 
+    /// Derive the key that would be used to send to another agent after a key exchange in
+    /// which we are the server side. Only meant for audits, e.g. to check interoperability
+    /// with other implementations.
+    /// @param {Base32} recipient_id - HCID encoded public encryption key of the other agent
+    /// @return {SecBuf} tx session key, matching the recipient's client side rx key
+    pub fn derive_session_tx(&mut self, recipient_id: &Base32) -> HcResult<SecBuf> {
+        debug!(
+            "derive tx session key of {} for {}",
+            self.public, recipient_id
+        );
+        let mut server_pk = self.decode_pub_key_into_secbuf();
+        let mut client_pk = utils::decode_pub_key(recipient_id.clone(), Self::codec())?;
+        let mut rx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        let mut tx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        kx::server_session(
+            &mut server_pk,
+            &mut self.private,
+            &mut client_pk,
+            &mut rx,
+            &mut tx,
+        )?;
+        Ok(tx)
+    }
+
+    /// Derive the key that would be used to receive from another agent after a key exchange
+    /// in which we are the client side, the counterpart of derive_session_tx.
+    /// Only meant for audits.
+    /// @param {Base32} sender_id - HCID encoded public encryption key of the other agent
+    /// @return {SecBuf} rx session key
+    pub fn derive_client_session_rx(&mut self, sender_id: &Base32) -> HcResult<SecBuf> {
+        debug!("derive rx session key of {} for {}", self.public, sender_id);
+        let mut client_pk = self.decode_pub_key_into_secbuf();
+        let mut server_pk = utils::decode_pub_key(sender_id.clone(), Self::codec())?;
+        let mut rx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        let mut tx = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        kx::client_session(
+            &mut client_pk,
+            &mut self.private,
+            &mut server_pk,
+            &mut rx,
+            &mut tx,
+        )?;
+        Ok(rx)
+    }
+
+    /// Derive the next session key from the current one, e.g. after a number of messages.
+    /// Both sides advance in lockstep without another key exchange, and the old key
+    /// can not be recovered from the new one.
+    /// @param {SecBuf} old_secret - the current session key (see derive_session_tx)
+    /// @return {SecBuf} the next session key
+    pub fn rekey_session(old_secret: &mut SecBuf) -> HcResult<SecBuf> {
+        if old_secret.len() != kx::SESSIONKEYBYTES {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "session key should be {} bytes",
+                kx::SESSIONKEYBYTES
+            )));
+        }
+        let mut context = SeedContext::new(SESSION_REKEY_CTX).to_sec_buf();
+        let mut new_secret = SecBuf::with_secure(kx::SESSIONKEYBYTES);
+        kdf::derive(&mut new_secret, 1, &mut context, old_secret)?;
+        Ok(new_secret)
+    }
+
     // TODO: Encrypt and decrypt functions
 }
 
+/// kdf context of EncryptingKeyPair::rekey_session
+const SESSION_REKEY_CTX: [u8; 8] = *b"HCREKEY0";
+
 pub fn generate_random_sign_keypair() -> HcResult<SigningKeyPair> {
-    let mut seed = utils::generate_random_seed_buf();
+    let mut seed = utils::try_generate_random_seed_buf()?;
     SigningKeyPair::new_from_seed(&mut seed)
 }
 
 pub fn generate_random_enc_keypair() -> HcResult<EncryptingKeyPair> {
-    let mut seed = utils::generate_random_seed_buf();
+    let mut seed = utils::try_generate_random_seed_buf()?;
     EncryptingKeyPair::new_from_seed(&mut seed)
 }
 
@@ -194,6 +515,7 @@ pub fn generate_random_enc_keypair() -> HcResult<EncryptingKeyPair> {
 mod tests {
     use super::*;
     use crate::SEED_SIZE;
+    use holochain_sodium::aead;
 
     pub fn test_generate_random_sign_keypair() -> SigningKeyPair {
         generate_random_sign_keypair().unwrap()
@@ -203,6 +525,26 @@ mod tests {
         generate_random_enc_keypair().unwrap()
     }
 
+    #[test]
+    fn it_should_have_an_rng_to_generate_keys() {
+        assert!(crate::rng_available());
+        assert!(utils::try_generate_random_seed_buf().is_ok());
+    }
+
+    #[test]
+    fn it_should_fail_key_generation_without_rng() {
+        let expected = Err(HolochainError::ErrorGeneric(
+            "Cannot generate keys: the system RNG is not available".to_string(),
+        ));
+        crate::with_rng_failure(|| {
+            assert!(!crate::rng_available());
+            assert_eq!(expected, utils::try_generate_random_seed_buf().map(|_| ()));
+            assert_eq!(expected, generate_random_sign_keypair().map(|_| ()));
+            assert_eq!(expected, generate_random_enc_keypair().map(|_| ()));
+        });
+        assert!(crate::rng_available());
+    }
+
     #[test]
     fn keypair_should_construct_and_clone_sign() {
         let mut keys = test_generate_random_sign_keypair();
@@ -217,6 +559,27 @@ mod tests {
         assert!(keys.new_from_self().unwrap().is_same(&mut keys));
     }
 
+    #[test]
+    fn keypair_should_compare_private_keys() {
+        let mut keys = test_generate_random_sign_keypair();
+        let mut other = test_generate_random_sign_keypair();
+        assert!(!keys.is_same(&mut other));
+
+        // same public key but a different private key:
+        let mut private = SecBuf::with_secure(keys.private.len());
+        private.randomize();
+        let mut forged = SigningKeyPair::new(keys.public.clone(), private);
+        assert!(!keys.is_same(&mut forged));
+
+        // a truncated private key never matches:
+        let mut short = SecBuf::with_secure(SEED_SIZE);
+        short
+            .write(0, &keys.private.read_lock()[..SEED_SIZE])
+            .unwrap();
+        let mut truncated = SigningKeyPair::new(keys.public.clone(), short);
+        assert!(!keys.is_same(&mut truncated));
+    }
+
     #[test]
     fn keypair_should_construct_and_clone_enc() {
         let mut keys = test_generate_random_enc_keypair();
@@ -243,19 +606,173 @@ mod tests {
         let mut signature = sign_keys.sign(&mut message).unwrap();
         println!("signature = {:?}", signature);
         // authentify signature
-        let succeeded = sign_keys.verify(&mut message, &mut signature);
+        let succeeded = sign_keys.verify(&mut message, &mut signature).unwrap();
         assert!(succeeded);
 
         // Create random data
         let mut random_signature = SecBuf::with_insecure(SIGNATURE_SIZE);
         random_signature.randomize();
         // authentify random signature
-        let succeeded = sign_keys.verify(&mut message, &mut random_signature);
+        let succeeded = sign_keys
+            .verify(&mut message, &mut random_signature)
+            .unwrap();
         assert!(!succeeded);
 
         // Randomize data again
         message.randomize();
-        let succeeded = sign_keys.verify(&mut message, &mut signature);
+        let succeeded = sign_keys.verify(&mut message, &mut signature).unwrap();
         assert!(!succeeded);
+
+        // a buffer of the wrong size is no signature at all
+        let mut short_signature = SecBuf::with_insecure(SIGNATURE_SIZE / 2);
+        assert!(sign_keys
+            .verify(&mut message, &mut short_signature)
+            .is_err());
+    }
+
+    #[test]
+    fn keypair_should_not_verify_signature_in_other_context() {
+        let mut sign_keys = test_generate_random_sign_keypair();
+        let mut message = SecBuf::with_insecure(16);
+        message.randomize();
+
+        let mut signature = sign_keys.sign_with_context("A", &mut message).unwrap();
+        assert!(sign_keys.verify_with_context("A", &mut message, &mut signature));
+        assert!(!sign_keys.verify_with_context("B", &mut message, &mut signature));
+        assert!(!sign_keys.verify(&mut message, &mut signature).unwrap());
+        let public = sign_keys.public.clone();
+        assert!(
+            utils::verify_bufs_with_context(public.clone(), "A", &mut message, &mut signature)
+                .unwrap()
+        );
+        assert!(
+            !utils::verify_bufs_with_context(public, "B", &mut message, &mut signature).unwrap()
+        );
+
+        let mut plain = sign_keys.sign(&mut message).unwrap();
+        assert!(!sign_keys.verify_with_context("A", &mut message, &mut plain));
+    }
+
+    #[test]
+    fn keypair_should_roundtrip_pairing_code() {
+        let sign_keys = test_generate_random_sign_keypair();
+        let code = sign_keys.pairing_code();
+        assert!(code
+            .chars()
+            .all(|c| c == '-' || BASE32_ALPHABET.contains(&(c as u8))));
+        assert!(code.split('-').all(|group| group.len() <= 4));
+        assert_eq!(
+            sign_keys.public,
+            SigningKeyPair::id_from_pairing_code(&code).unwrap()
+        );
+        // typed in sloppily:
+        let sloppy = code.to_lowercase().replace("-", " ");
+        assert_eq!(
+            sign_keys.public,
+            SigningKeyPair::id_from_pairing_code(&sloppy).unwrap()
+        );
+    }
+
+    #[test]
+    fn keypair_should_reject_corrupted_pairing_code() {
+        let sign_keys = test_generate_random_sign_keypair();
+        let code = sign_keys.pairing_code();
+        let first = code.chars().next().unwrap();
+        let corrupted = format!("{}{}", if first == 'A' { 'B' } else { 'A' }, &code[1..]);
+        assert!(SigningKeyPair::id_from_pairing_code(&corrupted).is_err());
+        assert!(SigningKeyPair::id_from_pairing_code(&code[5..]).is_err());
+        assert!(SigningKeyPair::id_from_pairing_code("not a code!").is_err());
+    }
+
+    #[test]
+    fn keypair_should_bind_handle_and_verify() {
+        let mut sign_keys = test_generate_random_sign_keypair();
+        let id = sign_keys.public();
+        let mut commitment = sign_keys.bind_handle("alice").unwrap();
+        assert_eq!("alice", commitment.handle);
+        assert!(verify_handle(&mut commitment, &id).unwrap());
+
+        // not valid for somebody else's id
+        let other = test_generate_random_sign_keypair().public();
+        assert!(!verify_handle(&mut commitment, &other).unwrap());
+
+        // nor for an altered handle
+        commitment.handle = "alicia".to_string();
+        assert!(!verify_handle(&mut commitment, &id).unwrap());
+
+        assert!(sign_keys.bind_handle("").is_err());
+    }
+
+    #[test]
+    fn keypair_should_derive_matching_session_keys() {
+        let mut server = test_generate_random_enc_keypair();
+        let mut client = test_generate_random_enc_keypair();
+        let other = test_generate_random_enc_keypair();
+
+        let mut tx = server.derive_session_tx(&client.public).unwrap();
+        let mut rx = client.derive_client_session_rx(&server.public).unwrap();
+        assert_eq!(kx::SESSIONKEYBYTES, tx.len());
+        assert_eq!(0, tx.compare(&mut rx));
+
+        let mut other_tx = server.derive_session_tx(&other.public).unwrap();
+        assert_ne!(0, tx.compare(&mut other_tx));
+    }
+
+    #[test]
+    fn keypair_should_rekey_sessions_in_lockstep() {
+        let mut server = test_generate_random_enc_keypair();
+        let mut client = test_generate_random_enc_keypair();
+        let mut tx = server.derive_session_tx(&client.public).unwrap();
+        let mut rx = client.derive_client_session_rx(&server.public).unwrap();
+
+        let mut next_tx = EncryptingKeyPair::rekey_session(&mut tx).unwrap();
+        let mut next_rx = EncryptingKeyPair::rekey_session(&mut rx).unwrap();
+        assert_eq!(0, next_tx.compare(&mut next_rx));
+        assert_ne!(0, next_tx.compare(&mut tx));
+
+        // the sides keep communicating with the new key:
+        let mut message = SecBuf::with_insecure_from_string("after rekeying".to_string());
+        let mut nonce = SecBuf::with_insecure(aead::NONCEBYTES);
+        nonce.randomize();
+        let mut cipher = SecBuf::with_insecure(message.len() + aead::ABYTES);
+        aead::enc(&mut message, &mut next_tx, None, &mut nonce, &mut cipher).unwrap();
+        let mut decrypted = SecBuf::with_insecure(message.len());
+        aead::dec(&mut decrypted, &mut next_rx, None, &mut nonce, &mut cipher).unwrap();
+        assert_eq!(0, decrypted.compare(&mut message));
+
+        let mut short = SecBuf::with_insecure(16);
+        assert!(EncryptingKeyPair::rekey_session(&mut short).is_err());
+    }
+
+    #[test]
+    fn keypair_should_sign_expiring_and_verify() {
+        let mut sign_keys = test_generate_random_sign_keypair();
+        let mut message = SecBuf::with_insecure(16);
+        message.randomize();
+
+        let mut signature = sign_keys.sign_expiring(&mut message, 1000).unwrap();
+        assert_eq!(1000, signature.expires_at);
+
+        // valid and not expired yet
+        assert!(sign_keys.verify_expiring(&mut signature, &mut message, 999));
+        // valid but expired
+        assert!(!sign_keys.verify_expiring(&mut signature, &mut message, 1000));
+        assert!(!sign_keys.verify_expiring(&mut signature, &mut message, 5000));
+
+        // the expiry is signed and can't be extended
+        signature.expires_at = 10000;
+        assert!(!sign_keys.verify_expiring(&mut signature, &mut message, 999));
+        signature.expires_at = 1000;
+
+        // a plain signature over the data does not count as an expiring one
+        let mut plain = ExpiringSignature {
+            signature: sign_keys.sign(&mut message).unwrap(),
+            expires_at: 1000,
+        };
+        assert!(!sign_keys.verify_expiring(&mut plain, &mut message, 999));
+
+        // nor does the expiring signature verify for other data
+        message.randomize();
+        assert!(!sign_keys.verify_expiring(&mut signature, &mut message, 999));
     }
 }
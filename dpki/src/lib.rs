@@ -9,6 +9,8 @@ extern crate lazy_static;
 pub const CONTEXT_SIZE: usize = 8;
 pub const SEED_SIZE: usize = 32;
 pub const AGENT_ID_CTX: [u8; 8] = *b"HCAGNTID";
+/// salt of the master seed commitment carried by derived KeyBundles
+pub(crate) const MASTER_COMMITMENT_SALT: [u8; 8] = *b"HCMASTER";
 pub(crate) const SIGNATURE_SIZE: usize = 64;
 
 lazy_static! {
@@ -18,9 +20,39 @@ lazy_static! {
         hcid::HcidEncoding::with_kind("hck0").expect("HCID failed miserably with_hck0.");
 }
 
+/// Check that the system RNG can be used before generating any keys.
+/// Key generation returns an error instead of proceeding when it is not.
+pub fn rng_available() -> bool {
+    #[cfg(test)]
+    {
+        if RNG_FAILURE.with(|failure| failure.get()) {
+            return false;
+        }
+    }
+    holochain_sodium::init_succeeded()
+}
+
+#[cfg(test)]
+thread_local! {
+    /// lets tests of the current thread see the RNG as unavailable
+    static RNG_FAILURE: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
+
+/// Runs the given function as if the system RNG was not available
+#[cfg(test)]
+pub(crate) fn with_rng_failure<T, F: FnOnce() -> T>(f: F) -> T {
+    RNG_FAILURE.with(|failure| failure.set(true));
+    let result = f();
+    RNG_FAILURE.with(|failure| failure.set(false));
+    result
+}
+
 pub mod key_blob;
 pub mod key_bundle;
 pub mod keypair;
+mod lru;
 pub mod password_encryption;
+pub mod revocation;
 pub mod seed;
 pub mod utils;
+pub mod verify_cache;
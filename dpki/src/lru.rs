@@ -0,0 +1,58 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// Minimal least recently used map, holding at most `capacity` entries.
+/// Meant for small caches, lookups are linear in the number of entries.
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, V>,
+    usage: VecDeque<K>,
+}
+
+impl<K: Clone + Eq + Hash, V> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            usage: VecDeque::new(),
+        }
+    }
+
+    /// get an entry, marking it as the most recently used one
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+        if let Some(position) = self.usage.iter().position(|k| k == key) {
+            let key = self.usage.remove(position).expect("position must be valid");
+            self.usage.push_back(key);
+        }
+        self.entries.get(key)
+    }
+
+    /// insert an entry, evicting the least recently used ones if full
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.contains_key(&key) {
+            self.usage.retain(|k| k != &key);
+        }
+        while self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            match self.usage.pop_front() {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.usage.push_back(key.clone());
+        self.entries.insert(key, value);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
@@ -1,6 +1,10 @@
 use holochain_core_types::error::HcResult;
 use holochain_sodium::{aead, kx, pwhash, secbuf::SecBuf};
 use serde_derive::{Deserialize, Serialize};
+use std::{
+    cmp,
+    time::{Duration, Instant},
+};
 
 pub type OpsLimit = u64;
 pub type MemLimit = usize;
@@ -96,6 +100,60 @@ pub(crate) fn pw_dec(
     Ok(())
 }
 
+/// Find pwhash settings that take about the given time on this machine, e.g. to make
+/// blobs as hard to brute force as the wait for unlocking them allows.
+/// Starting from the interactive limits, the memory limit and then the ops limit get
+/// increased until hashing takes at least `target`, but never beyond the sensitive limits.
+/// @param {Duration} target - how long hashing a passphrase may take
+/// @return {PwHashConfig} the settings to pass to as_blob / from_blob
+pub fn calibrate_kdf(target: Duration) -> HcResult<PwHashConfig> {
+    calibrate_kdf_with(target, measure_kdf)
+}
+
+/// Same as calibrate_kdf, but with the given function telling how long hashing takes
+/// with some settings instead of measuring it with the actual KDF.
+/// @param {Duration} target - how long hashing a passphrase may take
+/// @param {FnMut} measure - cost of hashing with the given settings
+/// @return {PwHashConfig} the settings to pass to as_blob / from_blob
+pub fn calibrate_kdf_with<F>(target: Duration, mut measure: F) -> HcResult<PwHashConfig>
+where
+    F: FnMut(&PwHashConfig) -> HcResult<Duration>,
+{
+    let mut config = PwHashConfig(
+        pwhash::OPSLIMIT_INTERACTIVE,
+        pwhash::MEMLIMIT_INTERACTIVE,
+        pwhash::ALG_ARGON2ID13,
+    );
+    while measure(&config)? < target {
+        if config.1 < pwhash::MEMLIMIT_SENSITIVE {
+            config.1 = cmp::min(config.1 * 2, pwhash::MEMLIMIT_SENSITIVE);
+        } else if config.0 < pwhash::OPSLIMIT_SENSITIVE {
+            config.0 += 1;
+        } else {
+            break;
+        }
+    }
+    Ok(config)
+}
+
+/// How long hashing a passphrase with the given settings takes on this machine
+/// @param {PwHashConfig} config - the settings to measure
+pub fn measure_kdf(config: &PwHashConfig) -> HcResult<Duration> {
+    let mut passphrase = SecBuf::with_insecure(pwhash::HASHBYTES);
+    passphrase.randomize();
+    let mut salt = SecBuf::with_insecure(pwhash::SALTBYTES);
+    salt.randomize();
+    let mut hash_result = SecBuf::with_secure(pwhash::HASHBYTES);
+    let start = Instant::now();
+    pw_hash(
+        &mut passphrase,
+        &mut salt,
+        &mut hash_result,
+        Some(config.clone()),
+    )?;
+    Ok(start.elapsed())
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
@@ -179,4 +237,50 @@ pub(crate) mod tests {
         assert!(hashed_password_c.compare(&mut hashed_password_b) == 0);
     }
 
+    /// 10ms per interactive memory limit per op, instead of actually hashing
+    fn fake_cost(config: &PwHashConfig) -> HcResult<Duration> {
+        let mem_factor = (config.1 / pwhash::MEMLIMIT_INTERACTIVE) as u32;
+        Ok(Duration::from_millis(10) * mem_factor * config.0 as u32)
+    }
+
+    #[test]
+    fn it_should_calibrate_kdf_to_target_time() {
+        let interactive = TEST_CONFIG.unwrap();
+        let interactive_cost = fake_cost(&interactive).unwrap();
+
+        let config = calibrate_kdf_with(interactive_cost, fake_cost).unwrap();
+        assert_eq!(interactive.0, config.0);
+        assert_eq!(interactive.1, config.1);
+
+        // memory gets raised first
+        let config = calibrate_kdf_with(interactive_cost * 3, fake_cost).unwrap();
+        assert_eq!(interactive.0, config.0);
+        assert_eq!(interactive.1 * 4, config.1);
+
+        // ops only once memory is at the sensitive limit
+        let max_mem_cost = fake_cost(&PwHashConfig(
+            interactive.0,
+            pwhash::MEMLIMIT_SENSITIVE,
+            interactive.2,
+        ))
+        .unwrap();
+        let config =
+            calibrate_kdf_with(max_mem_cost + Duration::from_millis(1), fake_cost).unwrap();
+        assert_eq!(interactive.0 + 1, config.0);
+        assert_eq!(pwhash::MEMLIMIT_SENSITIVE, config.1);
+
+        // never beyond the sensitive limits
+        let config = calibrate_kdf_with(Duration::from_secs(3600), fake_cost).unwrap();
+        assert_eq!(pwhash::OPSLIMIT_SENSITIVE, config.0);
+        assert_eq!(pwhash::MEMLIMIT_SENSITIVE, config.1);
+    }
+
+    #[test]
+    fn it_should_calibrate_kdf_with_the_actual_kdf() {
+        let interactive = TEST_CONFIG.unwrap();
+        let config = calibrate_kdf(Duration::from_millis(0)).unwrap();
+        assert_eq!(interactive.0, config.0);
+        assert_eq!(interactive.1, config.1);
+    }
+
 }
@@ -0,0 +1,137 @@
+//! Revocation of capability signatures, i.e. signatures an agent issued to grant access.
+//! The list only holds hashes of the revoked signatures and can itself be signed
+//! by the issuer, so it can be published and checked by others.
+
+use crate::{
+    keypair::SigningKeyPair,
+    utils::{self, sha256_of},
+};
+use holochain_core_types::{agent::Base32, error::HcResult};
+use holochain_sodium::{hash, secbuf::SecBuf};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// SHA-256 hashes of revoked signatures.
+/// Kept ordered so that the signed bytes of a list don't depend on the order of revocation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RevocationList {
+    revoked: BTreeSet<Vec<u8>>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        RevocationList::default()
+    }
+
+    /// revoke a signature, from now on verify_capability fails for it
+    /// @param {SecBuf} signature - the signature to revoke
+    pub fn revoke(&mut self, signature: &mut SecBuf) -> HcResult<()> {
+        self.revoked.insert(sha256_of(signature)?);
+        Ok(())
+    }
+
+    /// @param {SecBuf} signature - the signature to look up
+    /// @return true if the signature was revoked
+    pub fn is_revoked(&self, signature: &mut SecBuf) -> HcResult<bool> {
+        Ok(self.revoked.contains(&sha256_of(signature)?))
+    }
+
+    /// Verify a capability signature of the expected issuer that was not revoked
+    /// @param {str} issuer_id - HCID encoded public signing key of the issuer
+    /// @param {SecBuf} signature - the capability signature
+    /// @param {SecBuf} data - the data the capability signature is over
+    /// @return false if the signature was revoked or does not verify
+    pub fn verify_capability(
+        &self,
+        issuer_id: &str,
+        signature: &mut SecBuf,
+        data: &mut SecBuf,
+    ) -> HcResult<bool> {
+        if self.is_revoked(signature)? {
+            return Ok(false);
+        }
+        utils::verify_from(issuer_id, signature, data)
+    }
+
+    /// Sign the list, so that it can be published
+    /// @param {SigningKeyPair} issuer - the keys of the agent that issued the capabilities
+    /// @return {SecBuf} signature over all revoked hashes
+    pub fn sign(&self, issuer: &mut SigningKeyPair) -> HcResult<SecBuf> {
+        issuer.sign(&mut self.signed_bytes())
+    }
+
+    /// Verify a published list was signed by the issuer
+    /// @param {Base32} issuer_id - HCID encoded public signing key of the issuer
+    /// @param {SecBuf} signature - as returned by sign
+    /// @return true if verification succeeded
+    pub fn verify(&self, issuer_id: &Base32, signature: &mut SecBuf) -> HcResult<bool> {
+        utils::verify_from(issuer_id, signature, &mut self.signed_bytes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    /// the concatenated hashes, all of the same size
+    fn signed_bytes(&self) -> SecBuf {
+        let mut buf = SecBuf::with_insecure(self.revoked.len() * hash::BYTES256);
+        for (i, hash) in self.revoked.iter().enumerate() {
+            buf.write(i * hash::BYTES256, hash)
+                .expect("buffer is big enough for all hashes");
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::generate_random_sign_keypair;
+
+    #[test]
+    fn it_should_fail_revoked_capabilities() {
+        let mut issuer = generate_random_sign_keypair().unwrap();
+        let mut grant = SecBuf::with_insecure_from_string("a capability".to_string());
+        let mut capability = issuer.sign(&mut grant).unwrap();
+        let mut revocations = RevocationList::new();
+
+        assert!(revocations
+            .verify_capability(&issuer.public, &mut capability, &mut grant)
+            .unwrap());
+
+        revocations.revoke(&mut capability).unwrap();
+        assert!(revocations.is_revoked(&mut capability).unwrap());
+        assert!(!revocations
+            .verify_capability(&issuer.public, &mut capability, &mut grant)
+            .unwrap());
+
+        // other capabilities of the same issuer are not affected
+        let mut other_grant = SecBuf::with_insecure_from_string("another one".to_string());
+        let mut other = issuer.sign(&mut other_grant).unwrap();
+        assert!(revocations
+            .verify_capability(&issuer.public, &mut other, &mut other_grant)
+            .unwrap());
+    }
+
+    #[test]
+    fn it_should_sign_the_revocation_list() {
+        let mut issuer = generate_random_sign_keypair().unwrap();
+        let mut revocations = RevocationList::new();
+        let mut capability = SecBuf::with_insecure(64);
+        capability.randomize();
+        revocations.revoke(&mut capability).unwrap();
+
+        let mut signature = revocations.sign(&mut issuer).unwrap();
+        assert!(revocations.verify(&issuer.public, &mut signature).unwrap());
+
+        // revoking more invalidates the signature of the published list
+        capability.randomize();
+        revocations.revoke(&mut capability).unwrap();
+        assert_eq!(2, revocations.len());
+        assert!(!revocations.verify(&issuer.public, &mut signature).unwrap());
+    }
+}
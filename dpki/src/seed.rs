@@ -1,8 +1,8 @@
 use crate::{
     key_bundle::KeyBundle,
     password_encryption::*,
-    utils::{generate_derived_seed_buf, SeedContext},
-    AGENT_ID_CTX, SEED_SIZE,
+    utils::{generate_derived_seed_buf, sha256_of, SeedContext},
+    AGENT_ID_CTX, MASTER_COMMITMENT_SALT, SEED_SIZE,
 };
 use bip39::{Language, Mnemonic};
 use holochain_core_types::error::{HcResult, HolochainError};
@@ -82,10 +82,12 @@ impl Seed {
             HolochainError::ErrorGeneric(format!("Error loading Mnemonic phrase: {}", e))
         })?;
 
-        let entropy = mnemonic.entropy().to_owned();
+        // Write the entropy straight into secure memory instead of going through
+        // an owned Vec which would leave a copy of the seed on the heap
+        let entropy = mnemonic.entropy();
         assert_eq!(entropy.len(), SEED_SIZE);
         let mut seed_buf = SecBuf::with_secure(entropy.len());
-        seed_buf.from_array(entropy.as_slice())?;
+        seed_buf.from_array(entropy)?;
         // Done
         Ok(Seed {
             kind: seed_type,
@@ -162,7 +164,18 @@ impl RootSeed {
     ) -> HcResult<DeviceSeed> {
         let device_seed_buf =
             generate_derived_seed_buf(&mut self.inner.buf, seed_context, index, SEED_SIZE)?;
-        Ok(DeviceSeed::new(device_seed_buf))
+        let mut device_seed = DeviceSeed::new(device_seed_buf);
+        device_seed.master_commitment = Some(self.master_commitment()?);
+        Ok(device_seed)
+    }
+
+    /// Salted hash of this seed, handed down to every derived seed and KeyBundle
+    /// so that their common lineage can be checked without the seed itself.
+    pub fn master_commitment(&mut self) -> HcResult<Vec<u8>> {
+        let mut salted_seed = SecBuf::with_secure(MASTER_COMMITMENT_SALT.len() + SEED_SIZE);
+        salted_seed.write(0, &MASTER_COMMITMENT_SALT)?;
+        salted_seed.write(MASTER_COMMITMENT_SALT.len(), &self.inner.buf.read_lock())?;
+        sha256_of(&mut salted_seed)
     }
 }
 
@@ -173,6 +186,7 @@ impl RootSeed {
 #[derive(Debug)]
 pub struct DeviceSeed {
     inner: Seed,
+    master_commitment: Option<Vec<u8>>,
 }
 
 impl SeedTrait for DeviceSeed {
@@ -189,6 +203,7 @@ impl DeviceSeed {
     pub fn new(seed_buf: SecBuf) -> Self {
         DeviceSeed {
             inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::Device),
+            master_commitment: None,
         }
     }
 
@@ -202,7 +217,9 @@ impl DeviceSeed {
     ) -> HcResult<DevicePinSeed> {
         let mut hash = SecBuf::with_secure(pwhash::HASHBYTES);
         pw_hash(pin, &mut self.inner.buf, &mut hash, config)?;
-        Ok(DevicePinSeed::new(hash))
+        let mut device_pin_seed = DevicePinSeed::new(hash);
+        device_pin_seed.master_commitment = self.master_commitment.clone();
+        Ok(device_pin_seed)
     }
 }
 
@@ -213,6 +230,7 @@ impl DeviceSeed {
 #[derive(Debug)]
 pub struct DevicePinSeed {
     inner: Seed,
+    master_commitment: Option<Vec<u8>>,
 }
 
 impl SeedTrait for DevicePinSeed {
@@ -229,6 +247,7 @@ impl DevicePinSeed {
     pub fn new(seed_buf: SecBuf) -> Self {
         DevicePinSeed {
             inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::DevicePin),
+            master_commitment: None,
         }
     }
 
@@ -244,7 +263,9 @@ impl DevicePinSeed {
         let mut context = context.to_sec_buf();
         kdf::derive(&mut dna_seed_buf, index, &mut context, &mut self.inner.buf)?;
 
-        Ok(KeyBundle::new_from_seed_buf(&mut dna_seed_buf)?)
+        let mut bundle = KeyBundle::new_from_seed_buf(&mut dna_seed_buf)?;
+        bundle.master_commitment = self.master_commitment.clone();
+        Ok(bundle)
     }
 }
 
@@ -256,6 +277,7 @@ impl DevicePinSeed {
 mod tests {
     use super::*;
     use crate::{
+        key_bundle::same_master,
         password_encryption::tests::TEST_CONFIG,
         utils::{self, generate_random_seed_buf},
         SEED_SIZE,
@@ -340,6 +362,37 @@ mod tests {
         assert!(!keybundle_5.is_same(&mut keybundle_1));
     }
 
+    #[test]
+    fn it_should_tell_bundles_of_the_same_master() {
+        let context = SeedContext::new(*b"HCDEVICE");
+        let mut pin = generate_random_seed_buf();
+        let mut rs = RootSeed::new(generate_random_seed_buf());
+        let mut ds_1 = rs.generate_device_seed(&context, 1).unwrap();
+        let mut ds_2 = rs.generate_device_seed(&context, 2).unwrap();
+        let mut dps_1 = ds_1
+            .generate_device_pin_seed(&mut pin, TEST_CONFIG)
+            .unwrap();
+        let mut dps_2 = ds_2
+            .generate_device_pin_seed(&mut pin, TEST_CONFIG)
+            .unwrap();
+        let bundle_a = dps_1.generate_dna_key(1).unwrap();
+        let bundle_b = dps_2.generate_dna_key(7).unwrap();
+        assert!(same_master(&bundle_a, &bundle_b).unwrap());
+
+        let mut other_rs = RootSeed::new(generate_random_seed_buf());
+        let mut other_ds = other_rs.generate_device_seed(&context, 1).unwrap();
+        let mut other_dps = other_ds
+            .generate_device_pin_seed(&mut pin, TEST_CONFIG)
+            .unwrap();
+        let unrelated = other_dps.generate_dna_key(1).unwrap();
+        assert!(!same_master(&bundle_a, &unrelated).unwrap());
+
+        // bundles built straight from a seed buffer have no lineage to compare:
+        let mut seed_buf = generate_random_seed_buf();
+        let orphan = KeyBundle::new_from_seed_buf(&mut seed_buf).unwrap();
+        assert!(same_master(&bundle_a, &orphan).is_err());
+    }
+
     #[test]
     fn it_should_roundtrip_mnemonic() {
         let mut seed_buf = SecBuf::with_insecure(SEED_SIZE);
@@ -356,6 +409,7 @@ mod tests {
         let mut seed_2 = Seed::new_with_mnemonic(mnemonic, SeedType::Root).unwrap();
         assert_eq!(seed.kind, seed_2.kind);
         assert_eq!(0, seed.buf.compare(&mut seed_2.buf));
+        assert!(seed_2.buf.is_secure());
     }
 
     #[test]
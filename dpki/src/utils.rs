@@ -1,6 +1,7 @@
 use crate::{
+    lru::LruCache,
     password_encryption::{pw_dec, pw_enc, EncryptedData, PwHashConfig},
-    CODEC_HCS0, CONTEXT_SIZE, SEED_SIZE,
+    CODEC_HCS0, CONTEXT_SIZE, SEED_SIZE, SIGNATURE_SIZE,
 };
 use hcid::*;
 use holochain_core_types::{
@@ -9,8 +10,21 @@ use holochain_core_types::{
     error::{HcResult, HolochainError},
     signature::{Provenance, Signature},
 };
-use holochain_sodium::{kdf, secbuf::SecBuf, sign};
-use std::str;
+use holochain_sodium::{hash, kdf, secbuf::SecBuf, sign};
+use log::trace;
+use multihash::Hash;
+use std::{str, sync::Mutex};
+
+/// how many decoded signing keys `verify_bufs` keeps around
+const DECODED_SIGN_KEYS_CACHE_SIZE: usize = 256;
+
+lazy_static! {
+    /// HCID decoding includes error correction, which is wasted work when the same
+    /// agents' signatures get verified over and over. These are public keys,
+    /// so keeping them around is safe.
+    static ref DECODED_SIGN_KEYS: Mutex<LruCache<Base32, Vec<u8>>> =
+        Mutex::new(LruCache::new(DECODED_SIGN_KEYS_CACHE_SIZE));
+}
 
 /// a trait for things that have a provenance that can be verified
 pub trait Verify {
@@ -37,6 +51,30 @@ pub(crate) fn decode_pub_key(pub_key_b32: Base32, codec: &HcidEncoding) -> HcRes
     Ok(pub_key_sec)
 }
 
+/// Same as `decode_pub_key` with the signing key codec, but answered from a
+/// cache for ids that were decoded recently
+pub(crate) fn decode_sign_pub_key_cached(pub_key_b32: Base32) -> HcResult<SecBuf> {
+    let cached = DECODED_SIGN_KEYS
+        .lock()
+        .expect("decoded key cache poisoned")
+        .get(&pub_key_b32)
+        .cloned();
+    let pub_key = match cached {
+        Some(pub_key) => pub_key,
+        None => {
+            let pub_key = CODEC_HCS0.decode(&pub_key_b32)?;
+            DECODED_SIGN_KEYS
+                .lock()
+                .expect("decoded key cache poisoned")
+                .insert(pub_key_b32, pub_key.clone());
+            pub_key
+        }
+    };
+    let mut pub_key_sec = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
+    pub_key_sec.from_array(&pub_key)?;
+    Ok(pub_key_sec)
+}
+
 /// Encode with HCID a public key given as a SecBuf
 /// @param {SecBuf} pub_key_sec - Public signing key to encode
 /// @param {HcidEncoding} codec - The configured HCID encoder to use
@@ -61,17 +99,40 @@ pub fn verify(source: Address, data: String, signature: Signature) -> HcResult<b
     verify_bufs(source.to_string(), &mut message_buf, &mut signature_buf)
 }
 
+/// Fails for signature buffers that can't hold a signature at all, so that a caller passing
+/// the wrong buffer gets told instead of just seeing the verification fail.
+pub(crate) fn check_signature_size(signature: &SecBuf) -> HcResult<()> {
+    if signature.len() != SIGNATURE_SIZE {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Malformed signature: {} bytes instead of {}",
+            signature.len(),
+            SIGNATURE_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// SHA-256 of the given buffer as plain bytes, e.g. to use as a map key
+pub(crate) fn sha256_of(buf: &mut SecBuf) -> HcResult<Vec<u8>> {
+    let mut digest = SecBuf::with_insecure(hash::BYTES256);
+    hash::sha256(buf, &mut digest)?;
+    let digest = digest.read_lock().to_vec();
+    Ok(digest)
+}
+
 /// Verify data that was signed
 /// @param {Base32} pub_sign_key_b32 - Public signing key to verify with
 /// @param {SecBuf} data - Data buffer to verify
 /// @param {SecBuf} signature - Candidate signature for that data buffer
-/// @return true if verification succeeded
+/// @return true if verification succeeded, an error if the signature is malformed
 pub fn verify_bufs(
     pub_sign_key_b32: Base32,
     data: &mut SecBuf,
     signature: &mut SecBuf,
 ) -> HcResult<bool> {
-    let mut pub_key = decode_pub_key(pub_sign_key_b32, &CODEC_HCS0)?;
+    trace!("verify {} bytes with {}", data.len(), pub_sign_key_b32);
+    check_signature_size(signature)?;
+    let mut pub_key = decode_sign_pub_key_cached(pub_sign_key_b32)?;
     Ok(holochain_sodium::sign::verify(
         signature,
         data,
@@ -79,6 +140,59 @@ pub fn verify_bufs(
     ))
 }
 
+/// Verify data that was signed for a given purpose with SigningKeyPair::sign_with_context
+/// @param {Base32} pub_sign_key_b32 - Public signing key to verify with
+/// @param {str} context - the purpose the signature must have been made for
+/// @param {SecBuf} data - Data buffer to verify
+/// @param {SecBuf} signature - Candidate signature for that data buffer
+/// @return true if verification succeeded
+pub fn verify_bufs_with_context(
+    pub_sign_key_b32: Base32,
+    context: &str,
+    data: &mut SecBuf,
+    signature: &mut SecBuf,
+) -> HcResult<bool> {
+    let mut message = crate::keypair::context_message(context, data)?;
+    verify_bufs(pub_sign_key_b32, &mut message, signature)
+}
+
+/// Verify that data was signed by a specific expected agent
+/// @param {str} expected_id - HCID encoded public signing key of the expected signer
+/// @param {SecBuf} signature - Candidate signature for that data buffer
+/// @param {SecBuf} data - Data buffer to verify
+/// @return false on any mismatch: wrong signer, tampered data or malformed signature
+pub fn verify_from(expected_id: &str, signature: &mut SecBuf, data: &mut SecBuf) -> HcResult<bool> {
+    if signature.len() != SIGNATURE_SIZE {
+        return Ok(false);
+    }
+    verify_bufs(expected_id.to_string(), data, signature)
+}
+
+/// Verify a batch of signatures from any number of signers, e.g. a block of messages from
+/// many peers. Each signer's key gets decoded only once.
+/// @param {[(Base32, SecBuf, SecBuf)]} items - signer id, signature and signed data of each item
+/// @return whether each item verified, in the same order; items with a malformed signature
+///   or a signer id that can't be decoded don't verify, without affecting the other items.
+pub fn verify_mixed(items: &mut [(Base32, SecBuf, SecBuf)]) -> HcResult<Vec<bool>> {
+    Ok(items
+        .iter_mut()
+        .map(|(signer_id, signature, data)| {
+            verify_from(signer_id, signature, data).unwrap_or(false)
+        })
+        .collect())
+}
+
+/// Compute the content address of a public signing key id
+/// The decoded public key bytes are hashed the same way the CAS hashes content,
+/// so the same agent id always yields the same Address.
+/// @param {str} id - HCID encoded public signing key
+/// @return {Address} Content address of the decoded key
+pub fn address_of_id(id: &str) -> HcResult<Address> {
+    let mut pub_key = decode_pub_key(id.to_string(), &CODEC_HCS0)?;
+    let locker = pub_key.read_lock();
+    Ok(Address::encode_from_bytes(&locker[..], Hash::SHA2256))
+}
+
 pub struct SeedContext {
     inner: [u8; 8],
 }
@@ -120,9 +234,43 @@ pub fn generate_random_buf(size: usize) -> SecBuf {
     seed
 }
 
+/// the bitcoin base58 alphabet: no 0/O/I/l look-alikes and URL-safe
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// returns a random base58 identifier of `len` characters, e.g. for correlation ids
+pub fn random_id(len: usize) -> String {
+    // only use bytes below the largest multiple of 58 to avoid modulo bias
+    let limit = (256 / BASE58_ALPHABET.len() * BASE58_ALPHABET.len()) as u8;
+    let mut id = String::with_capacity(len);
+    while id.len() < len {
+        let mut buf = generate_random_buf(len);
+        let buf = buf.read_lock();
+        for byte in buf.iter().filter(|byte| **byte < limit) {
+            if id.len() == len {
+                break;
+            }
+            id.push(BASE58_ALPHABET[*byte as usize % BASE58_ALPHABET.len()] as char);
+        }
+    }
+    id
+}
+
+/// returns a random seed buf, or an error if the system RNG is not available
+pub fn try_generate_random_seed_buf() -> HcResult<SecBuf> {
+    if !crate::rng_available() {
+        return Err(HolochainError::ErrorGeneric(
+            "Cannot generate keys: the system RNG is not available".to_string(),
+        ));
+    }
+    Ok(generate_random_seed_buf())
+}
+
 /// returns a random seed buf
 pub fn generate_random_seed_buf() -> SecBuf {
-    generate_random_buf(SEED_SIZE)
+    // seeds are key material, so they never touch insecure memory
+    let mut seed = SecBuf::with_secure(SEED_SIZE);
+    seed.randomize();
+    seed
 }
 
 /// encrypt and base64 encode a secbuf
@@ -131,6 +279,7 @@ pub fn encrypt_with_passphrase_buf(
     passphrase: &mut SecBuf,
     config: Option<PwHashConfig>,
 ) -> HcResult<String> {
+    trace!("encrypt {} bytes with passphrase", data_buf.len());
     // encrypt buffer
     let encrypted_blob = pw_enc(data_buf, passphrase, config)?;
     // Serialize and convert to base64
@@ -145,14 +294,18 @@ pub fn decrypt_with_passphrase_buf(
     config: Option<PwHashConfig>,
     size: usize,
 ) -> HcResult<SecBuf> {
+    trace!("decrypt {} bytes with passphrase", size);
     // Decode base64
-    let blob_b64 = base64::decode(blob)?;
+    let blob_b64 = base64::decode(blob).map_err(|e| HolochainError::from(e).context("base64"))?;
     // Deserialize
-    let blob_json = str::from_utf8(&blob_b64)?;
-    let encrypted_blob: EncryptedData = serde_json::from_str(&blob_json)?;
+    let blob_json =
+        str::from_utf8(&blob_b64).map_err(|e| HolochainError::from(e).context("utf8"))?;
+    let encrypted_blob: EncryptedData =
+        serde_json::from_str(&blob_json).map_err(|e| HolochainError::from(e).context("json"))?;
     // Decrypt
     let mut decrypted_data = SecBuf::with_secure(size);
-    pw_dec(&encrypted_blob, passphrase, &mut decrypted_data, config)?;
+    pw_dec(&encrypted_blob, passphrase, &mut decrypted_data, config)
+        .map_err(|e| e.context("pw_dec"))?;
     // Check size
     if decrypted_data.len() != size {
         return Err(HolochainError::ErrorGeneric(
@@ -166,9 +319,55 @@ pub fn decrypt_with_passphrase_buf(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SIGNATURE_SIZE;
+    use crate::{
+        keypair::{generate_random_sign_keypair, SigningKeyPair},
+        password_encryption::tests::TEST_CONFIG,
+    };
     use holochain_sodium::{secbuf::SecBuf, sign};
 
+    lazy_static! {
+        static ref CAPTURED_EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    }
+
+    /// keeps every log event, since the logger is global these include the ones of other tests
+    struct CapturingLogger;
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            CAPTURED_EVENTS
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", record.level(), record.args()));
+        }
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger;
+
+    #[test]
+    fn it_should_trace_decrypt_without_secrets() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let secret = "top secret data!";
+        let mut data = SecBuf::with_insecure_from_string(secret.to_string());
+        let mut passphrase = SecBuf::with_insecure_from_string("my passphrase".to_string());
+        let blob = encrypt_with_passphrase_buf(&mut data, &mut passphrase, TEST_CONFIG).unwrap();
+        decrypt_with_passphrase_buf(&blob, &mut passphrase, TEST_CONFIG, secret.len()).unwrap();
+
+        let events = CAPTURED_EVENTS.lock().unwrap();
+        assert!(events.contains(&format!(
+            "TRACE decrypt {} bytes with passphrase",
+            secret.len()
+        )));
+        assert!(events
+            .iter()
+            .all(|event| !event.contains(secret) && !event.contains("my passphrase")));
+    }
+
     #[test]
     fn it_should_hcid_roundtrip() {
         let mut pub_sec_buf = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
@@ -183,6 +382,20 @@ mod tests {
         assert!(pub_sec_buf.compare(&mut roundtrip) == 0);
     }
 
+    #[test]
+    fn it_should_decode_cached_keys_like_uncached_ones() {
+        let mut pub_sec_buf = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
+        pub_sec_buf.randomize();
+        let pub_key_b32 = encode_pub_key(&mut pub_sec_buf, &CODEC_HCS0).unwrap();
+
+        let mut uncached = decode_pub_key(pub_key_b32.clone(), &CODEC_HCS0).unwrap();
+        let mut first = decode_sign_pub_key_cached(pub_key_b32.clone()).unwrap();
+        let mut second = decode_sign_pub_key_cached(pub_key_b32).unwrap();
+        assert_eq!(0, uncached.compare(&mut first));
+        assert_eq!(0, uncached.compare(&mut second));
+        assert_eq!(0, pub_sec_buf.compare(&mut second));
+    }
+
     #[test]
     fn it_should_verify_bufs() {
         let codec = HcidEncoding::with_kind("hcs0").expect("HCID failed miserably with_hcs0");
@@ -203,6 +416,88 @@ mod tests {
         assert!(res.unwrap());
     }
 
+    #[test]
+    fn it_should_generate_seeds_in_secure_memory() {
+        assert!(generate_random_seed_buf().is_secure());
+    }
+
+    #[test]
+    fn it_should_generate_random_ids() {
+        let id_1 = random_id(24);
+        let id_2 = random_id(24);
+        assert_eq!(24, id_1.len());
+        assert_eq!(24, id_2.len());
+        assert_ne!(id_1, id_2);
+        assert!(id_1.bytes().all(|c| BASE58_ALPHABET.contains(&c)));
+        assert_eq!("", random_id(0));
+    }
+
+    #[test]
+    fn it_should_verify_signatures_of_mixed_signers() {
+        let mut alice = generate_random_sign_keypair().unwrap();
+        let mut bob = generate_random_sign_keypair().unwrap();
+        let item = |keys: &mut SigningKeyPair, text: &str| {
+            let mut data = SecBuf::with_insecure_from_string(text.to_string());
+            let signature = keys.sign(&mut data).unwrap();
+            (keys.public.clone(), signature, data)
+        };
+        let mut items = vec![
+            item(&mut alice, "from alice"),
+            item(&mut bob, "from bob"),
+            item(&mut alice, "also from alice"),
+            item(&mut bob, "also from bob"),
+        ];
+        // claimed by the wrong signer:
+        items[2].0 = bob.public.clone();
+        // tampered with:
+        items[3].2 = SecBuf::with_insecure_from_string("not from bob".to_string());
+
+        assert_eq!(
+            vec![true, true, false, false],
+            verify_mixed(&mut items).unwrap()
+        );
+
+        // an undecodable signer only fails its own item:
+        items[0].0 = "not an id".to_string();
+        assert_eq!(
+            vec![false, true, false, false],
+            verify_mixed(&mut items).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_should_verify_from_expected_signer() {
+        let mut signer = generate_random_sign_keypair().unwrap();
+        let other = generate_random_sign_keypair().unwrap();
+        let mut data = SecBuf::with_insecure_from_string("a capability".to_string());
+        let mut signature = signer.sign(&mut data).unwrap();
+
+        assert!(verify_from(&signer.public, &mut signature, &mut data).unwrap());
+        assert!(!verify_from(&other.public, &mut signature, &mut data).unwrap());
+
+        let mut tampered = SecBuf::with_insecure_from_string("a capabilitY".to_string());
+        assert!(!verify_from(&signer.public, &mut signature, &mut tampered).unwrap());
+
+        let mut short_signature = SecBuf::with_insecure(SIGNATURE_SIZE - 1);
+        assert!(!verify_from(&signer.public, &mut short_signature, &mut data).unwrap());
+
+        assert!(verify_from("not an id", &mut signature, &mut data).is_err());
+    }
+
+    #[test]
+    fn it_should_derive_stable_address_of_id() {
+        let mut pub_key_1 = generate_random_buf(sign::PUBLICKEYBYTES);
+        let id_1 = encode_pub_key(&mut pub_key_1, &CODEC_HCS0).unwrap();
+        let mut pub_key_2 = generate_random_buf(sign::PUBLICKEYBYTES);
+        let id_2 = encode_pub_key(&mut pub_key_2, &CODEC_HCS0).unwrap();
+
+        let address_1 = address_of_id(&id_1).unwrap();
+        assert_eq!(address_1, address_of_id(&id_1).unwrap());
+        assert_ne!(address_1, address_of_id(&id_2).unwrap());
+
+        assert!(address_of_id("not an id").is_err());
+    }
+
     #[test]
     fn it_should_round_trip_passphrase_encryption() {
         let data_size = 32;
@@ -0,0 +1,132 @@
+//! A bounded cache of signature verification results, for signatures that get
+//! checked over and over again (e.g. the same entry arriving through gossip).
+
+use crate::{
+    lru::LruCache,
+    utils::{self, sha256_of},
+};
+use holochain_core_types::{agent::Base32, error::HcResult};
+use holochain_sodium::secbuf::SecBuf;
+
+/// (signer id, SHA-256 of the signature, SHA-256 of the data)
+/// Since the data hash is part of the key, tampered data never hits a cached result.
+type VerifyKey = (Base32, Vec<u8>, Vec<u8>);
+
+/// Least recently used cache of `verify_bufs` results.
+/// Holds at most `capacity` results, the least recently used one gets evicted first.
+pub struct VerifyCache {
+    results: LruCache<VerifyKey, bool>,
+    hits: u64,
+}
+
+impl VerifyCache {
+    pub fn new(capacity: usize) -> Self {
+        VerifyCache {
+            results: LruCache::new(capacity),
+            hits: 0,
+        }
+    }
+
+    /// Verify data that was signed, reusing a previous result for the very same
+    /// signer, signature and data if there is one.
+    /// @param {Base32} pub_sign_key_b32 - Public signing key to verify with
+    /// @param {SecBuf} data - Data buffer to verify
+    /// @param {SecBuf} signature - Candidate signature for that data buffer
+    /// @return true if verification succeeded
+    pub fn verify_cached(
+        &mut self,
+        pub_sign_key_b32: Base32,
+        data: &mut SecBuf,
+        signature: &mut SecBuf,
+    ) -> HcResult<bool> {
+        let key = (
+            pub_sign_key_b32.clone(),
+            sha256_of(signature)?,
+            sha256_of(data)?,
+        );
+        if let Some(result) = self.results.get(&key).cloned() {
+            self.hits += 1;
+            return Ok(result);
+        }
+        let result = utils::verify_bufs(pub_sign_key_b32, data, signature)?;
+        self.results.insert(key, result);
+        Ok(result)
+    }
+
+    /// number of verifications that were answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keypair::generate_random_sign_keypair, SIGNATURE_SIZE};
+
+    #[test]
+    fn it_should_answer_repeated_verifications_from_cache() {
+        let mut keys = generate_random_sign_keypair().unwrap();
+        let mut data = SecBuf::with_insecure(16);
+        data.randomize();
+        let mut signature = keys.sign(&mut data).unwrap();
+
+        let mut cache = VerifyCache::new(2);
+        assert!(cache
+            .verify_cached(keys.public.clone(), &mut data, &mut signature)
+            .unwrap());
+        assert_eq!(0, cache.hits());
+        assert!(cache
+            .verify_cached(keys.public.clone(), &mut data, &mut signature)
+            .unwrap());
+        assert_eq!(1, cache.hits());
+
+        // tampered data is a different key, and gets verified for real:
+        let mut tampered = SecBuf::with_insecure(16);
+        tampered.randomize();
+        assert!(!cache
+            .verify_cached(keys.public.clone(), &mut tampered, &mut signature)
+            .unwrap());
+        assert_eq!(1, cache.hits());
+    }
+
+    #[test]
+    fn it_should_evict_least_recently_used_results() {
+        let keys = generate_random_sign_keypair().unwrap();
+        let mut cache = VerifyCache::new(2);
+        let mut data: Vec<SecBuf> = (0..3)
+            .map(|_| {
+                let mut buf = SecBuf::with_insecure(16);
+                buf.randomize();
+                buf
+            })
+            .collect();
+        let mut signature = SecBuf::with_insecure(SIGNATURE_SIZE);
+        signature.randomize();
+
+        for buf in data.iter_mut() {
+            cache
+                .verify_cached(keys.public.clone(), buf, &mut signature)
+                .unwrap();
+        }
+        assert_eq!(2, cache.len());
+
+        // the first one got evicted, the last one is still cached:
+        cache
+            .verify_cached(keys.public.clone(), &mut data[2], &mut signature)
+            .unwrap();
+        assert_eq!(1, cache.hits());
+        cache
+            .verify_cached(keys.public.clone(), &mut data[0], &mut signature)
+            .unwrap();
+        assert_eq!(1, cache.hits());
+    }
+}
@@ -111,7 +111,10 @@ pub fn get_links_and_load(
         let get_type = get_result?.result;
         match get_type {
             GetEntryResultType::Single(elem) => Ok(elem.entry.unwrap().to_owned()),
-            GetEntryResultType::All(_) => Err(ZomeApiError::Internal("Invalid response. get_links_result returned all entries when latest was requested".to_string()))
+            GetEntryResultType::All(_) => Err(ZomeApiError::Internal("Invalid response. get_links_result returned all entries when latest was requested".to_string())),
+            GetEntryResultType::Multiple(_) => Err(ZomeApiError::Internal("Invalid response. get_links_result returned multiple entries for a single link target".to_string())),
+            GetEntryResultType::NotModified => Err(ZomeApiError::Internal("Invalid response. get_links_result returned not modified without a known revision".to_string())),
+            GetEntryResultType::TooLarge { .. } => Err(ZomeApiError::Internal("Invalid response. get_links_result returned too large without a size limit".to_string()))
         }
     })
     .collect();
@@ -69,6 +69,8 @@ pub fn sign_one_time<S: Into<String>>(payloads: Vec<S>) -> ZomeApiResult<SignOne
 }
 
 /// Verifies a provenance (public key, signature) against a payload
+/// Returns false if the signature does not match, and an error if the provenance is
+/// malformed, i.e. its source is not a valid agent id or its signature is not 64 bytes.
 /// # Examples
 /// ```rust
 /// # #![feature(try_from)]
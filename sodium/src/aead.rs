@@ -2,8 +2,15 @@
 
 use super::{check_init, secbuf::SecBuf};
 use crate::error::SodiumError;
+use std::collections::{HashSet, VecDeque};
 
 /// Used to set the size of nonce var in the enc fns
+///
+/// Reusing a nonce under the same secret breaks the encryption completely.
+/// XChaCha20 nonces are 24 bytes long, so they can safely be picked at random:
+/// a collision only gets likely after around 2^80 messages under one secret.
+/// Use a NonceTracker per secret to rule out reuse of recent nonces on top of that.
+/// Nonces of any other size are rejected.
 pub const NONCEBYTES: usize =
     rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_NPUBBYTES as usize;
 /// Used with the size of the message to set the size of the cipher and decripted message
@@ -11,6 +18,73 @@ pub const NONCEBYTES: usize =
 /// Note: look at the test cases to see how it is used
 pub const ABYTES: usize = rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_ABYTES as usize;
 
+fn check_nonce(nonce: &SecBuf) -> Result<(), SodiumError> {
+    if nonce.len() != NONCEBYTES {
+        return Err(SodiumError::Generic(format!(
+            "nonce should be {} bytes",
+            NONCEBYTES
+        )));
+    }
+    Ok(())
+}
+
+/// Hands out random nonces for one secret and remembers the most recent ones,
+/// regenerating a nonce in the (unlikely) case it collides with one of them.
+///
+/// Only tracks the last `capacity` nonces; a tracker must not be shared between secrets.
+pub struct NonceTracker {
+    capacity: usize,
+    seen: HashSet<Vec<u8>>,
+    order: VecDeque<Vec<u8>>,
+}
+
+impl NonceTracker {
+    /// @param {usize} capacity - how many recently used nonces to remember
+    pub fn new(capacity: usize) -> Self {
+        NonceTracker {
+            capacity,
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Fill the given buffer with a random nonce not handed out recently
+    ///
+    /// @param {SecBuf} nonce - NONCEBYTES long buffer to write the nonce to
+    pub fn next_nonce(&mut self, nonce: &mut SecBuf) -> Result<(), SodiumError> {
+        self.next_nonce_with(nonce, |nonce| nonce.randomize())
+    }
+
+    /// Same as next_nonce, but with the given generator instead of the system RNG
+    fn next_nonce_with<F>(&mut self, nonce: &mut SecBuf, mut generate: F) -> Result<(), SodiumError>
+    where
+        F: FnMut(&mut SecBuf),
+    {
+        check_nonce(nonce)?;
+        loop {
+            generate(nonce);
+            let bytes = nonce.read_lock().to_vec();
+            if !self.seen.contains(&bytes) {
+                self.remember(bytes);
+                return Ok(());
+            }
+        }
+    }
+
+    fn remember(&mut self, bytes: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(bytes.clone());
+        self.order.push_back(bytes);
+    }
+}
+
 /// Generate symmetric cipher text given a message, secret, and optional auth data
 ///
 /// @param {SecBuf} message - data to encrypt
@@ -30,6 +104,7 @@ pub fn enc(
     cipher: &mut SecBuf,
 ) -> Result<(), SodiumError> {
     check_init();
+    check_nonce(nonce)?;
     let my_adata_locker;
     let mut my_adata = std::ptr::null();
     let mut my_ad_len = 0 as libc::c_ulonglong;
@@ -80,6 +155,7 @@ pub fn dec(
     cipher: &mut SecBuf,
 ) -> Result<(), SodiumError> {
     check_init();
+    check_nonce(nonce)?;
     let my_adata_locker;
     let mut my_adata = std::ptr::null();
     let mut my_ad_len = 0 as libc::c_ulonglong;
@@ -111,6 +187,132 @@ pub fn dec(
     Ok(())
 }
 
+/// Encrypt a message in place, writing the authentication tag separately
+///
+/// Avoids allocating and copying into a combined cipher buffer for large messages.
+///
+/// @param {SecBuf} message - data to encrypt, overwritten with the cipher text
+///
+/// @param {SecBuf} secret - symmetric secret key
+///
+/// @param {SecBuf} adata - optional additional authenticated data
+///
+/// @param {SecBuf} nonce - sometimes called initialization vector (iv)
+///
+/// @param {SecBuf} mac - Empty Buffer of size ABYTES to receive the tag
+pub fn enc_detached(
+    message: &mut SecBuf,
+    secret: &mut SecBuf,
+    adata: Option<&mut SecBuf>,
+    nonce: &mut SecBuf,
+    mac: &mut SecBuf,
+) -> Result<(), SodiumError> {
+    check_init();
+    check_nonce(nonce)?;
+    if mac.len() != ABYTES {
+        return Err(SodiumError::OutputLength(format!(
+            "mac should be {} bytes",
+            ABYTES
+        )));
+    }
+    let my_adata_locker;
+    let mut my_adata = std::ptr::null();
+    let mut my_ad_len = 0 as libc::c_ulonglong;
+
+    if let Some(s) = adata {
+        my_adata_locker = s.read_lock();
+        my_adata = raw_ptr_char_immut!(my_adata_locker);
+        my_ad_len = my_adata_locker.len() as libc::c_ulonglong;
+    }
+
+    let mut message = message.write_lock();
+    let mut mac = mac.write_lock();
+    let nonce = nonce.read_lock();
+    let secret = secret.read_lock();
+    let message_len = message.len() as libc::c_ulonglong;
+    let message_ptr = raw_ptr_char!(message);
+
+    unsafe {
+        rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_encrypt_detached(
+            message_ptr,
+            raw_ptr_char!(mac),
+            std::ptr::null_mut(),
+            message_ptr,
+            message_len,
+            my_adata,
+            my_ad_len,
+            std::ptr::null_mut(),
+            raw_ptr_char_immut!(nonce),
+            raw_ptr_char_immut!(secret),
+        );
+    }
+    Ok(())
+}
+
+/// Decrypt in place a cipher text produced by enc_detached
+///
+/// Unlike dec, fails if the tag does not verify; the buffer is then left untouched.
+///
+/// @param {SecBuf} cipher - the cipher text, overwritten with the decrypted message
+///
+/// @param {SecBuf} secret - symmetric secret key
+///
+/// @param {SecBuf} adata - optional additional authenticated data
+///
+/// @param {SecBuf} nonce - sometimes called initialization vector (iv)
+///
+/// @param {SecBuf} mac - the authentication tag
+pub fn dec_detached(
+    cipher: &mut SecBuf,
+    secret: &mut SecBuf,
+    adata: Option<&mut SecBuf>,
+    nonce: &mut SecBuf,
+    mac: &mut SecBuf,
+) -> Result<(), SodiumError> {
+    check_init();
+    check_nonce(nonce)?;
+    if mac.len() != ABYTES {
+        return Err(SodiumError::OutputLength(format!(
+            "mac should be {} bytes",
+            ABYTES
+        )));
+    }
+    let my_adata_locker;
+    let mut my_adata = std::ptr::null();
+    let mut my_ad_len = 0 as libc::c_ulonglong;
+
+    if let Some(s) = adata {
+        my_adata_locker = s.read_lock();
+        my_adata = raw_ptr_char_immut!(my_adata_locker);
+        my_ad_len = my_adata_locker.len() as libc::c_ulonglong;
+    }
+
+    let mut cipher = cipher.write_lock();
+    let mac = mac.read_lock();
+    let nonce = nonce.read_lock();
+    let secret = secret.read_lock();
+    let cipher_len = cipher.len() as libc::c_ulonglong;
+    let cipher_ptr = raw_ptr_char!(cipher);
+
+    let res = unsafe {
+        rust_sodium_sys::crypto_aead_xchacha20poly1305_ietf_decrypt_detached(
+            cipher_ptr,
+            std::ptr::null_mut(),
+            cipher_ptr,
+            cipher_len,
+            raw_ptr_char_immut!(mac),
+            my_adata,
+            my_ad_len,
+            raw_ptr_char_immut!(nonce),
+            raw_ptr_char_immut!(secret),
+        )
+    };
+    match res {
+        0 => Ok(()),
+        _ => Err(SodiumError::new("aead detached decryption failed")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,7 +328,7 @@ mod tests {
         let mut adata = SecBuf::with_secure(16);
         adata.randomize();
 
-        let mut nonce = SecBuf::with_insecure(16);
+        let mut nonce = SecBuf::with_insecure(NONCEBYTES);
         nonce.randomize();
 
         let mut cipher = SecBuf::with_insecure(message.len() + ABYTES);
@@ -236,4 +438,123 @@ mod tests {
             format!("{:?}", *decrypted_message)
         );
     }
+
+    #[test]
+    fn it_should_detached_aead_encrypt_and_decrypt_in_place() {
+        let mut message = SecBuf::with_insecure(64);
+        message.randomize();
+        let mut original = message.clone();
+
+        let mut secret = SecBuf::with_secure(32);
+        secret.randomize();
+
+        let mut adata = SecBuf::with_insecure(16);
+        adata.randomize();
+
+        let mut nonce = SecBuf::with_insecure(NONCEBYTES);
+        nonce.randomize();
+
+        let mut mac = SecBuf::with_insecure(ABYTES);
+
+        let ptr_before = message.read_lock().as_ptr();
+        enc_detached(
+            &mut message,
+            &mut secret,
+            Some(&mut adata),
+            &mut nonce,
+            &mut mac,
+        )
+        .unwrap();
+        // encrypted in place: same buffer, same length, no tag appended
+        assert_eq!(ptr_before, message.read_lock().as_ptr());
+        assert_eq!(64, message.len());
+        assert!(message.compare(&mut original) != 0);
+
+        // a wrong adata fails and leaves the cipher text alone
+        let mut cipher = message.clone();
+        let mut bad_adata = SecBuf::with_insecure(16);
+        bad_adata.randomize();
+        assert!(dec_detached(
+            &mut message,
+            &mut secret,
+            Some(&mut bad_adata),
+            &mut nonce,
+            &mut mac,
+        )
+        .is_err());
+        assert_eq!(0, message.compare(&mut cipher));
+
+        dec_detached(
+            &mut message,
+            &mut secret,
+            Some(&mut adata),
+            &mut nonce,
+            &mut mac,
+        )
+        .unwrap();
+        assert_eq!(0, message.compare(&mut original));
+    }
+
+    #[test]
+    fn it_should_reject_bad_nonce_size() {
+        let mut message = SecBuf::with_insecure(16);
+        let mut secret = SecBuf::with_secure(32);
+        let mut nonce = SecBuf::with_insecure(16);
+        let mut cipher = SecBuf::with_insecure(16 + ABYTES);
+        assert!(enc(&mut message, &mut secret, None, &mut nonce, &mut cipher).is_err());
+        assert!(dec(&mut message, &mut secret, None, &mut nonce, &mut cipher).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_bad_mac_size_for_detached_aead() {
+        let mut message = SecBuf::with_insecure(16);
+        let mut secret = SecBuf::with_secure(32);
+        let mut nonce = SecBuf::with_insecure(NONCEBYTES);
+        let mut mac = SecBuf::with_insecure(ABYTES - 1);
+        assert!(enc_detached(&mut message, &mut secret, None, &mut nonce, &mut mac).is_err());
+        assert!(dec_detached(&mut message, &mut secret, None, &mut nonce, &mut mac).is_err());
+        let mut mac = SecBuf::with_insecure(ABYTES + 1);
+        assert!(dec_detached(&mut message, &mut secret, None, &mut nonce, &mut mac).is_err());
+    }
+
+    #[test]
+    fn it_should_regenerate_colliding_nonces() {
+        // seeded generator that repeats its first nonce once before moving on
+        let seeds = vec![7u8, 7, 8];
+        let mut next_seed = seeds.iter();
+        let mut seeded = |nonce: &mut SecBuf| {
+            let seed = *next_seed.next().unwrap();
+            nonce.write(0, &[seed; NONCEBYTES]).unwrap();
+        };
+
+        let mut tracker = NonceTracker::new(16);
+        let mut first = SecBuf::with_insecure(NONCEBYTES);
+        tracker.next_nonce_with(&mut first, &mut seeded).unwrap();
+        let mut second = SecBuf::with_insecure(NONCEBYTES);
+        tracker.next_nonce_with(&mut second, &mut seeded).unwrap();
+
+        assert_eq!(vec![7u8; NONCEBYTES], first.read_lock().to_vec());
+        assert_eq!(vec![8u8; NONCEBYTES], second.read_lock().to_vec());
+        assert!(next_seed.next().is_none());
+    }
+
+    #[test]
+    fn it_should_forget_nonces_beyond_capacity() {
+        let mut tracker = NonceTracker::new(1);
+        let mut nonce = SecBuf::with_insecure(NONCEBYTES);
+        tracker
+            .next_nonce_with(&mut nonce, |n| n.write(0, &[1; NONCEBYTES]).unwrap())
+            .unwrap();
+        tracker
+            .next_nonce_with(&mut nonce, |n| n.write(0, &[2; NONCEBYTES]).unwrap())
+            .unwrap();
+        // the first nonce was pushed out, so it is handed out again
+        tracker
+            .next_nonce_with(&mut nonce, |n| n.write(0, &[1; NONCEBYTES]).unwrap())
+            .unwrap();
+        assert_eq!(vec![1u8; NONCEBYTES], nonce.read_lock().to_vec());
+
+        let mut short = SecBuf::with_insecure(NONCEBYTES - 1);
+        assert!(tracker.next_nonce(&mut short).is_err());
+    }
 }
@@ -6,17 +6,19 @@ extern crate lazy_static;
 
 lazy_static! {
     /// we only need to call sodium_init once
-    static ref INIT: bool = {
-        unsafe {
-            rust_sodium_sys::sodium_init();
-        }
-        true
-    };
+    /// (it returns -1 if it could not be set up, e.g. when there is no usable RNG)
+    static ref INIT: bool = unsafe { rust_sodium_sys::sodium_init() >= 0 };
 }
 
 /// make sure sodium_init is called
 pub fn check_init() {
-    assert_eq!(true, *INIT);
+    assert!(*INIT, "sodium could not be initialized, is the system RNG available?");
+}
+
+/// true if sodium (and with it the system RNG) could be initialized,
+/// i.e. if calling into any of the other functions is safe
+pub fn init_succeeded() -> bool {
+    *INIT
 }
 
 /// make invoking ffi functions taking SecBuf references more readable
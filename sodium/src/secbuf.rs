@@ -205,6 +205,29 @@ impl SecBuf {
         }
     }
 
+    /// is this SecBuf backed by secure (mlocked / mprotected) memory?
+    pub fn is_secure(&self) -> bool {
+        self.t == SecurityType::Secure
+    }
+
+    /// move the content into secure memory, for insecure buffers that turn out to hold
+    /// sensitive data. The insecure memory gets zeroed.
+    /// Panics like with_secure() if the size is not a multiple of 8.
+    pub fn to_secure(mut self) -> SecBuf {
+        if self.is_secure() {
+            return self;
+        }
+        let mut secure = SecBuf::with_secure(self.len());
+        {
+            let content = self.read_lock();
+            secure
+                .write(0, &content)
+                .expect("secure buffer has the same size");
+        }
+        self.zero();
+        secure
+    }
+
     /// what is the current memory protection state of this SecBuf?
     pub fn protect_state(&self) -> ProtectState {
         self.p.clone()
@@ -378,6 +401,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_should_report_security_type() {
+        assert!(!SecBuf::with_insecure(16).is_secure());
+        assert!(!SecBuf::with_insecure_from_string("zooooo".to_string()).is_secure());
+        assert!(SecBuf::with_secure(16).is_secure());
+        assert!(SecBuf::with_secure(16).clone().is_secure());
+    }
+
+    #[test]
+    fn it_should_move_to_secure() {
+        let mut b = SecBuf::with_insecure(16);
+        b.write(0, &[1, 2, 3]).unwrap();
+        let mut c = b.to_secure();
+        assert!(c.is_secure());
+        {
+            let c = c.read_lock();
+            assert_eq!(&c[0..3], &[1, 2, 3]);
+        }
+        assert!(c.to_secure().is_secure());
+    }
+
     #[test]
     fn it_should_clone_insecure() {
         let mut b = SecBuf::with_insecure(16);
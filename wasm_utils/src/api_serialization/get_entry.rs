@@ -5,9 +5,11 @@ use holochain_core_types::{
     entry::{entry_type::EntryType, Entry, EntryWithMeta},
     error::HolochainError,
     json::*,
+    signature::Provenance,
     time::Timeout,
 };
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, time::Duration};
 
 #[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
 pub enum StatusRequestKind {
@@ -21,6 +23,50 @@ impl Default for StatusRequestKind {
     }
 }
 
+/// Structure used to specify if and how often a get that failed on the network
+/// (e.g. because it timed out) should be tried again.
+/// The delay before each retry doubles, starting with base_delay.
+/// The default is to not retry at all.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, PartialEq, Clone)]
+pub struct GetRetryOptions {
+    pub max_retries: usize,
+    pub base_delay: Timeout,
+}
+
+impl Default for GetRetryOptions {
+    fn default() -> Self {
+        GetRetryOptions {
+            max_retries: 0,
+            base_delay: Timeout::new(100),
+        }
+    }
+}
+
+impl GetRetryOptions {
+    pub fn new(max_retries: usize, base_delay: Timeout) -> Self {
+        GetRetryOptions {
+            max_retries,
+            base_delay,
+        }
+    }
+
+    /// returns how long to wait before retrying after the given (zero based) failed attempt,
+    /// or None if there are no retries left
+    pub fn delay_before_retry(&self, failed_attempt: usize) -> Option<Duration> {
+        if failed_attempt >= self.max_retries {
+            return None;
+        }
+        let base_delay: Duration = (&self.base_delay).into();
+        Some(base_delay * 2u32.saturating_pow(failed_attempt as u32))
+    }
+}
+
+/// Options that are off are left out when serializing GetEntryOptions,
+/// so that the wire format stays the same for callers not using them.
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 /// Structure used to specify what should be returned to a call to get_entry_result()
 /// The default is to return the latest entry.
 #[derive(Deserialize, Debug, Serialize, DefaultJson, PartialEq, Clone)]
@@ -29,6 +75,30 @@ pub struct GetEntryOptions {
     pub entry: bool,
     pub headers: bool,
     pub timeout: Timeout,
+    #[serde(default)]
+    pub retry: GetRetryOptions,
+    /// if set, found entries are not returned but only their size, which is
+    /// reported in GetEntryResultItem::entry_size
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub size_only: bool,
+    /// if set, the address of every found entry gets recomputed from its content and compared
+    /// to the address it was requested under, see GetEntryResult::content_verified
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub verify_content: bool,
+    /// if set, every found entry comes with an EntryProof, so that e.g. light clients can
+    /// check who published it without holding the DHT themselves
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub include_proof: bool,
+    /// address of the revision the caller already has. If the latest revision is still that
+    /// one, the result is GetEntryResultType::NotModified instead of the entry itself.
+    /// Only applies to StatusRequestKind::Latest requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_newer_than: Option<Address>,
+    /// upper bound for the summed up content size of all found entries, in bytes.
+    /// Larger results come back as GetEntryResultType::TooLarge instead of the entries.
+    /// Does not apply if size_only is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
 }
 
 impl Default for GetEntryOptions {
@@ -38,6 +108,12 @@ impl Default for GetEntryOptions {
             entry: true,
             headers: false,
             timeout: Default::default(),
+            retry: Default::default(),
+            size_only: false,
+            verify_content: false,
+            include_proof: false,
+            if_newer_than: None,
+            max_bytes: None,
         }
     }
 }
@@ -54,6 +130,12 @@ impl GetEntryOptions {
             entry,
             headers,
             timeout,
+            retry: Default::default(),
+            size_only: false,
+            verify_content: false,
+            include_proof: false,
+            if_newer_than: None,
+            max_bytes: None,
         }
     }
 }
@@ -71,6 +153,31 @@ pub struct EntryResultMeta {
     pub crud_status: CrudStatus,
 }
 
+/// Evidence of who published an entry: the header it was committed with and the
+/// provenance of the committing agent in it, which is a signature of the entry address.
+/// Check it with hc_dpki's Verify trait: `proof.provenance.verify(address.to_string())`
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct EntryProof {
+    pub header: ChainHeader,
+    pub provenance: Provenance,
+}
+
+impl EntryProof {
+    /// picks the header of the given entry out of the ones found with it.
+    /// The committing agent's provenance is the last one, it gets added after
+    /// all provenances passed into the commit.
+    pub fn from_headers(entry_address: &Address, headers: &[ChainHeader]) -> Option<Self> {
+        let header = headers
+            .iter()
+            .find(|header| header.entry_address() == entry_address)?;
+        let provenance = header.provenances().last()?.clone();
+        Some(EntryProof {
+            header: header.clone(),
+            provenance,
+        })
+    }
+}
+
 /// Structure that holds data returned from a get entry request.
 /// When the meta is None, we know the entry wasn't found.  This is
 /// because at the very least the entry_type and the address will be
@@ -80,6 +187,12 @@ pub struct GetEntryResultItem {
     pub meta: Option<EntryResultMeta>,
     pub entry: Option<Entry>,
     pub headers: Vec<ChainHeader>, // headers if requested in options
+    /// size of the entry's content in bytes, only set if size_only was requested in options
+    #[serde(default)]
+    pub entry_size: Option<usize>,
+    /// only set if include_proof was requested in options
+    #[serde(default)]
+    pub proof: Option<EntryProof>,
 }
 impl GetEntryResultItem {
     pub fn new(maybe_entry_with_meta: Option<(&EntryWithMeta, Vec<ChainHeader>)>) -> Self {
@@ -92,14 +205,33 @@ impl GetEntryResultItem {
                 }),
                 entry: Some(entry_with_meta.entry.clone()),
                 headers,
+                entry_size: None,
+                proof: None,
             },
             _ => GetEntryResultItem {
                 meta: None,
                 entry: None,
                 headers: Vec::new(),
+                entry_size: None,
+                proof: None,
             },
         }
     }
+
+    /// size of the entry's content in bytes, 0 if there is no entry
+    pub fn content_size(&self) -> u64 {
+        self.entry
+            .as_ref()
+            .map(|entry| String::from(entry.content()).len() as u64)
+            .unwrap_or(0)
+    }
+
+    /// replaces the entry with the size of its content
+    pub fn strip_to_size(&mut self) {
+        if let Some(entry) = self.entry.take() {
+            self.entry_size = Some(String::from(entry.content()).len());
+        }
+    }
 }
 
 /// Structure that holds a whole crud status history if the status request
@@ -128,11 +260,25 @@ impl EntryHistory {
 pub enum GetEntryResultType {
     Single(GetEntryResultItem),
     All(EntryHistory),
+    /// all entries an address resolves to, for addresses that can match more than one
+    /// (e.g. aggregated link bases). Clients iterate them, there is no particular order.
+    Multiple(Vec<GetEntryResultItem>),
+    /// the latest revision is the one given in GetEntryOptions::if_newer_than
+    NotModified,
+    /// the found entries add up to more than GetEntryOptions::max_bytes,
+    /// size is their summed up content size in bytes
+    TooLarge {
+        size: u64,
+    },
 }
 
 #[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
 pub struct GetEntryResult {
     pub result: GetEntryResultType,
+    /// None if content verification was not requested in GetEntryOptions,
+    /// otherwise whether the content of all returned entries matches their address
+    #[serde(default)]
+    pub content_verified: Option<bool>,
 }
 impl GetEntryResult {
     pub fn new(
@@ -143,6 +289,7 @@ impl GetEntryResult {
             StatusRequestKind::All => {
                 let mut entry_result = GetEntryResult {
                     result: GetEntryResultType::All(EntryHistory::new()),
+                    content_verified: None,
                 };
                 if let Some((entry_with_meta, headers)) = maybe_entry_with_meta_and_headers {
                     entry_result.push(entry_with_meta, headers);
@@ -153,42 +300,147 @@ impl GetEntryResult {
                 result: GetEntryResultType::Single(GetEntryResultItem::new(
                     maybe_entry_with_meta_and_headers,
                 )),
+                content_verified: None,
             },
         }
     }
+
+    /// a result holding all the given matches, see GetEntryResultType::Multiple
+    pub fn with_matches(items: Vec<GetEntryResultItem>) -> Self {
+        GetEntryResult {
+            result: GetEntryResultType::Multiple(items),
+            content_verified: None,
+        }
+    }
+
     pub fn found(&self) -> bool {
         match self.result {
             GetEntryResultType::Single(ref item) => item.meta.is_some(),
             GetEntryResultType::All(ref history) => !history.items.is_empty(),
+            GetEntryResultType::Multiple(ref items) => !items.is_empty(),
+            GetEntryResultType::NotModified | GetEntryResultType::TooLarge { .. } => true,
+        }
+    }
+
+    /// true if the caller's known revision is still the latest one
+    pub fn not_modified(&self) -> bool {
+        match self.result {
+            GetEntryResultType::NotModified => true,
+            _ => false,
+        }
+    }
+
+    /// replaces the found entry, see GetEntryOptions::if_newer_than
+    pub fn mark_not_modified(&mut self) {
+        self.result = GetEntryResultType::NotModified;
+    }
+
+    /// the size of the found entries if they were held back, see GetEntryOptions::max_bytes
+    pub fn too_large(&self) -> Option<u64> {
+        match self.result {
+            GetEntryResultType::TooLarge { size } => Some(size),
+            _ => None,
+        }
+    }
+
+    /// summed up content size of all found entries in bytes
+    pub fn content_size(&self) -> u64 {
+        match self.result {
+            GetEntryResultType::Single(ref item) => item.content_size(),
+            GetEntryResultType::All(ref history) => history
+                .items
+                .iter()
+                .map(GetEntryResultItem::content_size)
+                .sum(),
+            GetEntryResultType::Multiple(ref items) => {
+                items.iter().map(GetEntryResultItem::content_size).sum()
+            }
+            GetEntryResultType::NotModified | GetEntryResultType::TooLarge { .. } => 0,
+        }
+    }
+
+    /// replaces the found entries if they add up to more than max_bytes,
+    /// see GetEntryOptions::max_bytes
+    pub fn limit_size(&mut self, max_bytes: u64) {
+        let size = self.content_size();
+        if size > max_bytes {
+            self.result = GetEntryResultType::TooLarge { size };
         }
     }
 
     /// clears the entry result to be equivalent to not found
     pub fn clear(&mut self) {
         match self.result {
-            GetEntryResultType::Single(_) => {
+            GetEntryResultType::Single(_)
+            | GetEntryResultType::NotModified
+            | GetEntryResultType::TooLarge { .. } => {
                 self.result = GetEntryResultType::Single(GetEntryResultItem::new(None))
             }
             GetEntryResultType::All(ref mut history) => history.items.clear(),
+            GetEntryResultType::Multiple(ref mut items) => items.clear(),
         };
     }
 
     /// adds an item to history, or if Single, writes over the current value of the item
     pub fn push(&mut self, entry_with_meta: &EntryWithMeta, headers: Vec<ChainHeader>) {
         match self.result {
-            GetEntryResultType::Single(_) => {
+            GetEntryResultType::Single(_)
+            | GetEntryResultType::NotModified
+            | GetEntryResultType::TooLarge { .. } => {
                 self.result = GetEntryResultType::Single(GetEntryResultItem::new(Some((
                     entry_with_meta,
                     headers,
                 ))))
             }
             GetEntryResultType::All(ref mut history) => history.push(entry_with_meta, headers),
+            GetEntryResultType::Multiple(ref mut items) => {
+                items.push(GetEntryResultItem::new(Some((entry_with_meta, headers))))
+            }
+        };
+    }
+
+    /// records the outcome of checking a returned entry's content against the address it
+    /// was requested under. A single mismatch marks the whole result as not verified.
+    pub fn record_content_verification(&mut self, verified: bool) {
+        self.content_verified = Some(self.content_verified.unwrap_or(true) && verified);
+    }
+
+    /// attaches the proof to the most recently pushed entry, see GetEntryOptions::include_proof
+    pub fn record_proof(&mut self, proof: Option<EntryProof>) {
+        let item = match self.result {
+            GetEntryResultType::Single(ref mut item) => Some(item),
+            GetEntryResultType::All(ref mut history) => history.items.last_mut(),
+            GetEntryResultType::Multiple(ref mut items) => items.last_mut(),
+            GetEntryResultType::NotModified | GetEntryResultType::TooLarge { .. } => None,
+        };
+        if let Some(item) = item {
+            item.proof = proof;
+        }
+    }
+
+    /// replaces all found entries with their size, see GetEntryOptions::size_only
+    pub fn strip_to_size(&mut self) {
+        match self.result {
+            GetEntryResultType::Single(ref mut item) => item.strip_to_size(),
+            GetEntryResultType::All(ref mut history) => {
+                for item in history.items.iter_mut() {
+                    item.strip_to_size();
+                }
+            }
+            GetEntryResultType::Multiple(ref mut items) => {
+                for item in items.iter_mut() {
+                    item.strip_to_size();
+                }
+            }
+            GetEntryResultType::NotModified | GetEntryResultType::TooLarge { .. } => (),
         };
     }
 
-    /// returns the entry searched for.  Note that if the GetEntryOptions did not
+    /// returns the entry searched for, for Multiple results the last one of the matches.
+    /// Note that if the GetEntryOptions did not
     /// include a request for the entry value, this function will return None even if the
-    /// entry was found.
+    /// entry was found. The same goes for NotModified results, the caller already has the entry,
+    /// and for TooLarge results.
     pub fn latest(&self) -> Option<Entry> {
         match self.result {
             GetEntryResultType::Single(ref item) => item.entry.clone(),
@@ -196,8 +448,73 @@ impl GetEntryResult {
                 let last = history.items.last()?;
                 last.entry.clone()
             }
+            GetEntryResultType::Multiple(ref items) => {
+                let last = items.last()?;
+                last.entry.clone()
+            }
+            GetEntryResultType::NotModified | GetEntryResultType::TooLarge { .. } => None,
         }
     }
+
+    /// deserializes the value of the entry searched for into the type the caller expects,
+    /// failing with a SerializationError if it is of a different shape or not an app entry.
+    /// returns Ok(None) whenever latest() returns None, e.g. if the entry was not found.
+    pub fn expect_type<T: DeserializeOwned>(&self) -> Result<Option<T>, HolochainError> {
+        let entry = match self.latest() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let entry_type = entry.entry_type();
+        match entry {
+            Entry::App(_, value) => serde_json::from_str(&String::from(value))
+                .map(Some)
+                .map_err(|error| {
+                    HolochainError::SerializationError(format!(
+                        "entry of type {} does not have the expected shape: {}",
+                        entry_type, error
+                    ))
+                }),
+            _ => Err(HolochainError::SerializationError(format!(
+                "expected an app entry but got an entry of type {}",
+                entry_type
+            ))),
+        }
+    }
+
+    /// the serialized entry searched for, None whenever latest() returns None,
+    /// e.g. if the entry was not found
+    pub fn into_option(self) -> Option<JsonString> {
+        self.latest().map(JsonString::from)
+    }
+}
+
+/// a plain entry lookup maps to a Latest result of a live entry without headers
+impl From<Option<Entry>> for GetEntryResult {
+    fn from(maybe_entry: Option<Entry>) -> Self {
+        let maybe_entry_with_meta = maybe_entry.map(|entry| EntryWithMeta {
+            entry,
+            crud_status: CrudStatus::Live,
+            maybe_link_update_delete: None,
+        });
+        GetEntryResult::new(
+            StatusRequestKind::Latest,
+            maybe_entry_with_meta
+                .as_ref()
+                .map(|entry_with_meta| (entry_with_meta, Vec::new())),
+        )
+    }
+}
+
+impl From<GetEntryResult> for Option<Entry> {
+    fn from(entry_result: GetEntryResult) -> Self {
+        entry_result.latest()
+    }
+}
+
+impl From<GetEntryResult> for Option<JsonString> {
+    fn from(entry_result: GetEntryResult) -> Self {
+        entry_result.into_option()
+    }
 }
 
 #[cfg(test)]
@@ -205,8 +522,9 @@ mod tests {
     use super::*;
     use holochain_core_types::{
         chain_header::test_chain_header,
-        entry::{test_entry, test_entry_a, test_entry_b},
+        entry::{entry_type::test_app_entry_type, test_entry, test_entry_a, test_entry_b},
     };
+    use std::convert::TryFrom;
 
     #[test]
     fn test_get_entry_result_found() {
@@ -286,4 +604,353 @@ mod tests {
         result.clear();
         assert!(!result.found());
     }
+
+    #[test]
+    fn test_get_entry_options_retry_serialization() {
+        let options = GetEntryOptions {
+            retry: GetRetryOptions::new(3, Timeout::new(250)),
+            ..Default::default()
+        };
+        let json = JsonString::from(options.clone());
+        assert_eq!(
+            JsonString::from_json(
+                "{\"status_request\":\"Latest\",\"entry\":true,\"headers\":false,\"timeout\":60000,\"retry\":{\"max_retries\":3,\"base_delay\":250}}"
+            ),
+            json,
+        );
+        assert_eq!(Ok(options), GetEntryOptions::try_from(json));
+
+        // options serialized before retries existed still deserialize, without retries
+        let legacy = JsonString::from_json(
+            "{\"status_request\":\"Latest\",\"entry\":true,\"headers\":false,\"timeout\":60000}",
+        );
+        assert_eq!(
+            Ok(GetRetryOptions::default()),
+            GetEntryOptions::try_from(legacy).map(|options| options.retry),
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        let retry = GetRetryOptions::new(3, Timeout::new(100));
+        assert_eq!(
+            Some(Duration::from_millis(100)),
+            retry.delay_before_retry(0)
+        );
+        assert_eq!(
+            Some(Duration::from_millis(200)),
+            retry.delay_before_retry(1)
+        );
+        assert_eq!(
+            Some(Duration::from_millis(400)),
+            retry.delay_before_retry(2)
+        );
+        assert_eq!(None, retry.delay_before_retry(3));
+        assert_eq!(None, GetRetryOptions::default().delay_before_retry(0));
+    }
+
+    #[test]
+    fn test_get_entry_size_only() {
+        let large_entry = Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from("x".repeat(100_000))),
+        );
+        let expected_size = String::from(large_entry.content()).len();
+        assert!(expected_size > 100_000);
+
+        let mut result = GetEntryResult::new(StatusRequestKind::Latest, None);
+        result.push(
+            &EntryWithMeta {
+                entry: large_entry,
+                crud_status: CrudStatus::Live,
+                maybe_link_update_delete: None,
+            },
+            vec![test_chain_header()],
+        );
+        result.strip_to_size();
+
+        assert!(result.found());
+        assert_eq!(result.latest(), None);
+        match result.result {
+            GetEntryResultType::Single(ref item) => {
+                assert_eq!(item.entry_size, Some(expected_size))
+            }
+            _ => unreachable!(),
+        }
+        // the content itself is not part of what gets transferred
+        assert!(String::from(JsonString::from(result)).len() < 100_000);
+    }
+
+    #[test]
+    fn test_get_entry_size_only_serialization() {
+        let options = GetEntryOptions {
+            size_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            Ok(options.clone()),
+            GetEntryOptions::try_from(JsonString::from(options)),
+        );
+
+        let item = GetEntryResultItem {
+            meta: None,
+            entry: None,
+            headers: Vec::new(),
+            entry_size: Some(42),
+            proof: None,
+        };
+        assert_eq!(
+            JsonString::from_json(
+                "{\"meta\":null,\"entry\":null,\"headers\":[],\"entry_size\":42,\"proof\":null}"
+            ),
+            JsonString::from(item),
+        );
+    }
+
+    #[test]
+    fn test_record_content_verification() {
+        let mut result = GetEntryResult::new(StatusRequestKind::All, None);
+        assert_eq!(result.content_verified, None);
+        result.record_content_verification(true);
+        assert_eq!(result.content_verified, Some(true));
+        result.record_content_verification(false);
+        assert_eq!(result.content_verified, Some(false));
+        result.record_content_verification(true);
+        assert_eq!(result.content_verified, Some(false));
+    }
+
+    #[test]
+    fn test_content_verified_serialization() {
+        let mut result = GetEntryResult::new(StatusRequestKind::Latest, None);
+        result.record_content_verification(false);
+        let json = JsonString::from(result);
+        assert_eq!(
+            JsonString::from_json(
+                "{\"result\":{\"Single\":{\"meta\":null,\"entry\":null,\"headers\":[],\"entry_size\":null,\"proof\":null}},\"content_verified\":false}"
+            ),
+            json,
+        );
+        let result = GetEntryResult::try_from(json).unwrap();
+        assert_eq!(result.content_verified, Some(false));
+
+        // results serialized without the field deserialize as unverified
+        let result = GetEntryResult::try_from(JsonString::from_json(
+            "{\"result\":{\"Single\":{\"meta\":null,\"entry\":null,\"headers\":[]}}}",
+        ))
+        .unwrap();
+        assert_eq!(result.content_verified, None);
+    }
+
+    #[test]
+    fn test_entry_proof_from_headers() {
+        let header = test_chain_header();
+        let proof = EntryProof::from_headers(&test_entry().address(), &[header.clone()]).unwrap();
+        assert_eq!(proof.header, header);
+        assert_eq!(&proof.provenance, header.provenances().last().unwrap());
+
+        assert_eq!(
+            None,
+            EntryProof::from_headers(&test_entry_a().address(), &[header])
+        );
+        assert_eq!(None, EntryProof::from_headers(&test_entry().address(), &[]));
+    }
+
+    #[test]
+    fn test_record_proof() {
+        let mut result = GetEntryResult::new(StatusRequestKind::Latest, None);
+        result.push(
+            &EntryWithMeta {
+                entry: test_entry(),
+                crud_status: CrudStatus::Live,
+                maybe_link_update_delete: None,
+            },
+            Vec::new(),
+        );
+        let proof = EntryProof::from_headers(&test_entry().address(), &[test_chain_header()]);
+        result.record_proof(proof.clone());
+        match result.result {
+            GetEntryResultType::Single(ref item) => assert_eq!(item.proof, proof),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let mut result = GetEntryResult::from(Some(test_entry()));
+        assert!(!result.not_modified());
+        result.mark_not_modified();
+        assert!(result.not_modified());
+        assert!(result.found());
+        assert_eq!(result.latest(), None);
+        result.clear();
+        assert!(!result.found());
+    }
+
+    #[test]
+    fn test_not_modified_serialization() {
+        let options = GetEntryOptions {
+            if_newer_than: Some(test_entry().address()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Ok(options.clone()),
+            GetEntryOptions::try_from(JsonString::from(options)),
+        );
+
+        let mut result = GetEntryResult::new(StatusRequestKind::Latest, None);
+        result.mark_not_modified();
+        let json = JsonString::from(result);
+        assert_eq!(
+            JsonString::from_json("{\"result\":\"NotModified\",\"content_verified\":null}"),
+            json,
+        );
+        assert!(GetEntryResult::try_from(json).unwrap().not_modified());
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut result = GetEntryResult::from(Some(test_entry()));
+        let size = result.content_size();
+        assert!(size > 0);
+        result.limit_size(size);
+        assert_eq!(result.too_large(), None);
+        assert_eq!(result.latest(), Some(test_entry()));
+
+        result.limit_size(size - 1);
+        assert_eq!(result.too_large(), Some(size));
+        assert!(result.found());
+        assert_eq!(result.latest(), None);
+    }
+
+    #[test]
+    fn test_too_large_serialization() {
+        let options = GetEntryOptions {
+            max_bytes: Some(1024),
+            ..Default::default()
+        };
+        assert_eq!(
+            Ok(options.clone()),
+            GetEntryOptions::try_from(JsonString::from(options)),
+        );
+        // options of older clients come without max_bytes:
+        let options = GetEntryOptions::try_from(JsonString::from_json(
+            "{\"status_request\":\"Latest\",\"entry\":true,\"headers\":false,\"timeout\":60000}",
+        ))
+        .unwrap();
+        assert_eq!(options.max_bytes, None);
+
+        let mut result = GetEntryResult::from(Some(test_entry()));
+        result.limit_size(0);
+        let json = JsonString::from(result);
+        assert_eq!(
+            JsonString::from_json(&format!(
+                "{{\"result\":{{\"TooLarge\":{{\"size\":{}}}}},\"content_verified\":null}}",
+                String::from(test_entry().content()).len()
+            )),
+            json,
+        );
+        assert!(GetEntryResult::try_from(json)
+            .unwrap()
+            .too_large()
+            .is_some());
+    }
+
+    #[test]
+    fn test_expect_type() {
+        let result = GetEntryResult::from(Some(test_entry()));
+        assert_eq!(
+            result.expect_type::<String>(),
+            Ok(Some("test entry value".to_string()))
+        );
+        match result.expect_type::<u64>() {
+            Err(HolochainError::SerializationError(message)) => {
+                assert!(message.starts_with("entry of type testEntryType"))
+            }
+            other => panic!("expected a SerializationError, got {:?}", other),
+        }
+
+        let result = GetEntryResult::from(None::<Entry>);
+        assert_eq!(result.expect_type::<String>(), Ok(None));
+    }
+
+    #[test]
+    fn test_multiple_serialization() {
+        let entries = vec![test_entry_a(), test_entry_b()];
+        let entries_with_meta: Vec<EntryWithMeta> = entries
+            .iter()
+            .map(|entry| EntryWithMeta {
+                entry: entry.clone(),
+                crud_status: CrudStatus::Live,
+                maybe_link_update_delete: None,
+            })
+            .collect();
+        let items: Vec<GetEntryResultItem> = entries_with_meta
+            .iter()
+            .map(|entry_with_meta| GetEntryResultItem::new(Some((entry_with_meta, Vec::new()))))
+            .collect();
+        let result = GetEntryResult::with_matches(items.clone());
+        assert!(result.found());
+        assert_eq!(result.latest(), Some(test_entry_b()));
+
+        let json = JsonString::from(result);
+        assert_eq!(
+            JsonString::from_json(&format!(
+                "{{\"result\":{{\"Multiple\":[{},{}]}},\"content_verified\":null}}",
+                String::from(JsonString::from(items[0].clone())),
+                String::from(JsonString::from(items[1].clone())),
+            )),
+            json,
+        );
+
+        let roundtrip = GetEntryResult::try_from(json).unwrap();
+        match roundtrip.result {
+            GetEntryResultType::Multiple(ref items) => {
+                let found: Vec<Option<Entry>> =
+                    items.iter().map(|item| item.entry.clone()).collect();
+                assert_eq!(found, vec![Some(test_entry_a()), Some(test_entry_b())]);
+            }
+            _ => panic!("expected a Multiple result"),
+        }
+
+        let empty = GetEntryResult::with_matches(Vec::new());
+        assert!(!empty.found());
+        assert_eq!(
+            JsonString::from_json("{\"result\":{\"Multiple\":[]},\"content_verified\":null}"),
+            JsonString::from(empty),
+        );
+    }
+
+    #[test]
+    fn test_get_entry_result_from_option_entry() {
+        let result = GetEntryResult::from(Some(test_entry()));
+        assert!(result.found());
+        assert_eq!(result.latest(), Some(test_entry()));
+        match result.result {
+            GetEntryResultType::Single(ref item) => {
+                let meta = item.meta.as_ref().unwrap();
+                assert_eq!(meta.address, test_entry().address());
+                assert_eq!(meta.crud_status, CrudStatus::Live);
+            }
+            _ => panic!("expected a Single result"),
+        }
+        assert_eq!(Option::<Entry>::from(result), Some(test_entry()));
+
+        let result = GetEntryResult::from(None::<Entry>);
+        assert!(!result.found());
+        assert_eq!(Option::<Entry>::from(result), None);
+    }
+
+    #[test]
+    fn test_get_entry_result_into_option_json() {
+        let result = GetEntryResult::from(Some(test_entry()));
+        assert_eq!(
+            Option::<JsonString>::from(result.clone()),
+            Some(JsonString::from(test_entry()))
+        );
+        assert_eq!(result.into_option(), Some(JsonString::from(test_entry())));
+
+        let result = GetEntryResult::from(None::<Entry>);
+        assert_eq!(Option::<JsonString>::from(result.clone()), None);
+        assert_eq!(result.into_option(), None);
+    }
 }
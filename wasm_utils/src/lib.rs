@@ -3,6 +3,7 @@
 //! Must not have any dependency with any other Holochain crates.
 #![feature(try_from)]
 #![warn(unused_extern_crates)]
+extern crate serde;
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;